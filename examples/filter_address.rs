@@ -2,6 +2,7 @@ use clap::Parser;
 use eyre::anyhow;
 use regex::Regex;
 use serde_json::json;
+use starknet::core::types::Felt;
 use tracing_subscriber::filter::LevelFilter;
 
 use std::collections::HashMap;
@@ -9,7 +10,11 @@ use std::fs;
 use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 
-use starknet_event_query::util::{parse_event, start_logger};
+use starknet_event_query::{
+    filter_seed::FilterSeed,
+    matcher::EventMatcher,
+    util::{parse_event, start_logger},
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -42,6 +47,7 @@ fn cond_refract(cli: &Cli, unfiltered_rx: &Regex, fixture: PathBuf) -> eyre::Res
         return Ok(());
     }
 
+    let filter_seed = FilterSeed::load(&fixture)?;
     let mut known_addresses = HashMap::new();
     let mut events = Vec::new();
     let source = fs::File::open(&fixture)?;
@@ -81,9 +87,14 @@ fn cond_refract(cli: &Cli, unfiltered_rx: &Regex, fixture: PathBuf) -> eyre::Res
         let output_name = format!("{}w{}.jsonl", stem, filter_no);
         let output_path = cli.fixture_dir.join(output_name);
         let mut output_file = fs::File::create(&output_path)?;
-        let address = serde_json::Value::String(addr.clone());
+        let matcher = EventMatcher::new(
+            filter_seed.from_block,
+            filter_seed.to_block,
+            Some(Felt::from_hex(&addr)?),
+            None,
+        );
         for event in events.iter() {
-            if event["from_address"] == address {
+            if matcher.is_match(event)? {
                 writeln!(&mut output_file, "{}", event)?;
             }
         }
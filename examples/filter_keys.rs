@@ -9,7 +9,11 @@ use std::fs;
 use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 
-use starknet_event_query::util::{parse_event, start_logger};
+use starknet_event_query::{
+    filter_seed::{FilterSeed, parse_filter},
+    matcher::EventMatcher,
+    util::{parse_event, start_logger},
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -17,7 +21,7 @@ pub struct Cli {
     #[arg(
         long,
         short = 'u',
-        long_help = "Filter for keys in any order",
+        long_help = "Accept the event's keys in any order at every position",
         default_value = "false"
     )]
     pub unordered: bool,
@@ -41,6 +45,7 @@ fn cond_refract(cli: &Cli, unfiltered_rx: &Regex, fixture: PathBuf) -> eyre::Res
         return Ok(());
     }
 
+    let filter_seed = FilterSeed::load(&fixture)?;
     let mut known_keys = HashSet::new();
     let mut events: Vec<serde_json::Value> = Vec::new();
     let source = fs::File::open(&fixture)?;
@@ -70,8 +75,9 @@ fn cond_refract(cli: &Cli, unfiltered_rx: &Regex, fixture: PathBuf) -> eyre::Res
                     .collect()
             };
             known_keys.insert(canon_keys);
-            events.push(event);
         }
+
+        events.push(event);
     }
 
     for (index, keys) in known_keys.into_iter().enumerate() {
@@ -91,19 +97,15 @@ fn cond_refract(cli: &Cli, unfiltered_rx: &Regex, fixture: PathBuf) -> eyre::Res
         let output_name = format!("{}w{}.jsonl", stem, filter_no);
         let output_path = cli.fixture_dir.join(output_name);
         let mut output_file = fs::File::create(&output_path)?;
+        let (_, matcher_keys) = parse_filter(&filter_json.to_string())?;
+        let matcher = EventMatcher::new(
+            filter_seed.from_block,
+            filter_seed.to_block,
+            None,
+            matcher_keys,
+        );
         for event in events.iter() {
-            let serde_json::Value::Array(ref event_keys) = event["keys"] else {
-                return Err(anyhow!("unexpected event keys type"));
-            };
-
-            let accept = if !cli.unordered {
-                event_keys.starts_with(&keys)
-            } else {
-                let event_key_set: HashSet<serde_json::Value> =
-                    event_keys.iter().cloned().collect();
-                keys.iter().all(|k| event_key_set.contains(k))
-            };
-            if accept {
+            if matcher.is_match(event)? {
                 writeln!(&mut output_file, "{}", event)?;
             }
         }
@@ -0,0 +1,93 @@
+use clap::Parser;
+use starknet::core::utils::cairo_short_string_to_felt;
+use tokio::net::TcpListener;
+use tracing_subscriber::filter::LevelFilter;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use starknet_event_query::{
+    fixture_set::FixtureSet,
+    mock_rpc::{Server, serve},
+    util::start_logger,
+};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[arg(
+        long,
+        value_name = "addr",
+        long_help = "Address to listen on",
+        default_value = "127.0.0.1:9545"
+    )]
+    pub listen: String,
+    #[arg(
+        long,
+        value_name = "n",
+        long_help = "Maximum accepted chunk size",
+        default_value = "1024"
+    )]
+    pub max_chunk_size: u64,
+    #[arg(
+        long,
+        value_name = "n",
+        long_help = "Maximum accepted number of keys in a filter",
+        default_value = "16"
+    )]
+    pub max_keys: usize,
+    #[arg(
+        long,
+        value_name = "version",
+        long_help = "Spec version reported by starknet_specVersion",
+        default_value = "0.8.1"
+    )]
+    pub spec_version: String,
+    #[arg(
+        long,
+        value_name = "name",
+        long_help = "Chain id reported by starknet_chainId, as short string",
+        default_value = "SN_MAIN"
+    )]
+    pub chain_id: String,
+    #[arg(
+        long,
+        value_name = "n",
+        long_help = "Last block of the pretended chain, to play a partially synced node (defaults to the last fixture block)"
+    )]
+    pub head: Option<u64>,
+    #[arg(
+        long,
+        value_name = "fixtures",
+        long_help = "Path to fixture directory",
+        default_value = "ground"
+    )]
+    pub fixture_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    start_logger(LevelFilter::INFO);
+    let cli = Cli::parse();
+
+    let fixtures = FixtureSet::load(&cli.fixture_dir)?;
+    tracing::info!("loaded {} fixtures", fixtures.entries.len());
+    let head = fixtures
+        .entries
+        .iter()
+        .filter_map(|e| e.to_block.number())
+        .max()
+        .unwrap_or_default();
+    let head = cli.head.unwrap_or(head);
+    let server = Arc::new(Server {
+        fixtures,
+        max_chunk_size: cli.max_chunk_size,
+        max_keys: cli.max_keys,
+        spec_version: cli.spec_version,
+        chain_id: cairo_short_string_to_felt(&cli.chain_id)?,
+        head,
+    });
+    let listener = TcpListener::bind(&cli.listen).await?;
+    tracing::info!("listening on {}", cli.listen);
+    serve(server, listener).await
+}
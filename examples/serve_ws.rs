@@ -0,0 +1,54 @@
+use clap::Parser;
+use tokio::net::TcpListener;
+use tracing_subscriber::filter::LevelFilter;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use starknet_event_query::{
+    fixture_set::FixtureSet,
+    mock_ws::{Server, load_reorg_script, serve},
+    util::start_logger,
+};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[arg(
+        long,
+        value_name = "addr",
+        long_help = "Address to listen on",
+        default_value = "127.0.0.1:9545"
+    )]
+    pub listen: String,
+    #[arg(
+        long,
+        value_name = "path",
+        long_help = "JSON array of reorgs to inject, each with an after_block and the reorg fields"
+    )]
+    pub reorg_script: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "fixtures",
+        long_help = "Path to fixture directory",
+        default_value = "ground"
+    )]
+    pub fixture_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    start_logger(LevelFilter::INFO);
+    let cli = Cli::parse();
+
+    let fixtures = FixtureSet::load(&cli.fixture_dir)?;
+    tracing::info!("loaded {} fixtures", fixtures.entries.len());
+    let reorgs = match &cli.reorg_script {
+        Some(path) => load_reorg_script(path)?,
+        None => Vec::new(),
+    };
+    let server = Arc::new(Server::new(fixtures, reorgs));
+    let listener = TcpListener::bind(&cli.listen).await?;
+    tracing::info!("listening on {}", cli.listen);
+    serve(server, listener).await
+}
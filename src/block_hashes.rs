@@ -0,0 +1,183 @@
+use eyre::anyhow;
+use starknet::core::types::Felt;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::felt::parse_felt;
+use crate::outcome::Violation;
+
+// block number -> block hash of the events of a range, stored next to its
+// fixtures as a JSON object, e.g. {"913": "0x5d3..."}
+#[derive(Default)]
+pub struct BlockHashes {
+    pub hashes: BTreeMap<u64, Felt>,
+}
+
+impl BlockHashes {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let raw: HashMap<String, String> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut hashes = BTreeMap::new();
+        for (block_number, hash) in raw {
+            let block_number = str::parse::<u64>(&block_number)
+                .map_err(|_| anyhow!("block number not a number: {}", block_number))?;
+            hashes.insert(block_number, Felt::from_hex(&hash)?);
+        }
+
+        Ok(Self { hashes })
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        let raw: BTreeMap<u64, String> = self
+            .hashes
+            .iter()
+            .map(|(block_number, hash)| (*block_number, format!("{:#x}", hash)))
+            .collect();
+        fs::write(path, serde_json::to_string_pretty(&raw)?)?;
+        Ok(())
+    }
+
+    // events of one block must agree on its hash
+    pub fn insert(&mut self, block_number: u64, hash: Felt) -> Result<(), Violation> {
+        match self.hashes.insert(block_number, hash) {
+            Some(previous) if previous != hash => Err(Violation(format!(
+                "block {} reported with hashes {:#x} and {:#x}",
+                block_number, previous, hash
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // an event with a block number names its block's hash, except spec 0.9
+    // events of the pre_confirmed block, which only a range ending at the
+    // pending tag has; pending events before 0.9 have neither
+    pub fn insert_event(
+        &mut self,
+        event: &serde_json::Value,
+        to_pending: bool,
+    ) -> eyre::Result<()> {
+        let Some(block_number) = event["block_number"].as_u64() else {
+            return Ok(());
+        };
+
+        match event["block_hash"].as_str() {
+            Some(hash) => Ok(self.insert(block_number, parse_felt(hash)?)?),
+            None if to_pending
+                && event["finality_status"]
+                    .as_str()
+                    .is_none_or(|status| status == "PRE_CONFIRMED") =>
+            {
+                Ok(())
+            }
+            None => {
+                Err(Violation(format!("event in block {} w/o block hash", block_number)).into())
+            }
+        }
+    }
+
+    // blocks the stored map doesn't know are left unchecked
+    pub fn check(&self, actual: &BlockHashes) -> Result<(), Violation> {
+        for (block_number, hash) in actual.hashes.iter() {
+            if let Some(expected) = self.hashes.get(block_number)
+                && expected != hash
+            {
+                return Err(Violation(format!(
+                    "block {} has hash {:#x}, expected {:#x}",
+                    block_number, hash, expected
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use std::fs;
+
+    use super::BlockHashes;
+
+    fn block_hashes(hashes: &[(u64, u64)]) -> BlockHashes {
+        let mut block_hashes = BlockHashes::default();
+        for (block_number, hash) in hashes {
+            block_hashes
+                .insert(*block_number, Felt::from(*hash))
+                .unwrap();
+        }
+        block_hashes
+    }
+
+    #[test]
+    fn block_has_one_hash() {
+        let mut hashes = block_hashes(&[(7, 0x70)]);
+        assert!(hashes.insert(7, Felt::from(0x70_u64)).is_ok());
+        let err = hashes.insert(7, Felt::from(0x71_u64)).unwrap_err();
+        assert_eq!(err.0, "block 7 reported with hashes 0x70 and 0x71");
+    }
+
+    #[test]
+    fn confirmed_events_need_a_hash() {
+        let mut hashes = BlockHashes::default();
+        let event = json!({ "block_number": 7, "block_hash": "0x70" });
+        hashes.insert_event(&event, false).unwrap();
+        assert_eq!(hashes.hashes.get(&7), Some(&Felt::from(0x70_u64)));
+
+        let unhashed = json!({ "block_number": 8 });
+        let err = hashes.insert_event(&unhashed, false).unwrap_err();
+        assert_eq!(err.to_string(), "event in block 8 w/o block hash");
+        let confirmed = json!({ "block_number": 8, "finality_status": "ACCEPTED_ON_L2" });
+        assert!(hashes.insert_event(&confirmed, true).is_err());
+        assert_eq!(hashes.hashes.len(), 1);
+    }
+
+    #[test]
+    fn pending_events_need_no_hash() {
+        let mut hashes = BlockHashes::default();
+        hashes.insert_event(&json!({}), false).unwrap();
+        hashes
+            .insert_event(&json!({ "block_number": 8 }), true)
+            .unwrap();
+        let pre_confirmed = json!({ "block_number": 8, "finality_status": "PRE_CONFIRMED" });
+        hashes.insert_event(&pre_confirmed, true).unwrap();
+        assert!(hashes.hashes.is_empty());
+    }
+
+    #[test]
+    fn checks_known_blocks_only() {
+        let expected = block_hashes(&[(7, 0x70), (8, 0x80)]);
+        assert!(expected.check(&block_hashes(&[(7, 0x70)])).is_ok());
+        assert!(
+            expected
+                .check(&block_hashes(&[(8, 0x80), (9, 0x90)]))
+                .is_ok()
+        );
+        let err = expected
+            .check(&block_hashes(&[(7, 0x70), (8, 0x81)]))
+            .unwrap_err();
+        assert_eq!(err.0, "block 8 has hash 0x81, expected 0x80");
+    }
+
+    #[test]
+    fn saved_hashes_load_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("7+1h.json");
+        let hashes = block_hashes(&[(7, 0x70), (8, 0x80)]);
+        hashes.save(&path).unwrap();
+        assert_eq!(BlockHashes::load(&path).unwrap().hashes, hashes.hashes);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("7h.json");
+        for contents in [r#"{"seven": "0x70"}"#, r#"{"7": "0xzz"}"#, r#"{"7": 70}"#] {
+            fs::write(&path, contents).unwrap();
+            assert!(BlockHashes::load(&path).is_err(), "{}", contents);
+        }
+    }
+}
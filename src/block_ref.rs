@@ -0,0 +1,201 @@
+use eyre::anyhow;
+use serde_json::json;
+use starknet::core::types::{BlockId, BlockTag, ConfirmedBlockId, Felt};
+
+use std::fmt;
+
+use crate::spec::SpecVersion;
+
+// a fixture range endpoint; in JSON a block number, a tag name or
+// {"block_hash": "0x..."}
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlockRef {
+    Number(u64),
+    Hash(Felt),
+    Latest,
+    // spec 0.9 calls the pending block pre_confirmed, both names are
+    // accepted
+    Pending,
+    L1Accepted,
+}
+
+impl BlockRef {
+    pub fn from_json(value: &serde_json::Value) -> eyre::Result<Self> {
+        if let Some(n) = value.as_u64() {
+            return Ok(Self::Number(n));
+        }
+
+        if let Some(tag) = value.as_str() {
+            return match tag {
+                "latest" => Ok(Self::Latest),
+                "pending" | "pre_confirmed" => Ok(Self::Pending),
+                "l1_accepted" => Ok(Self::L1Accepted),
+                _ => Err(anyhow!("unknown block tag: {}", tag)),
+            };
+        }
+
+        if let Some(hash) = value["block_hash"].as_str() {
+            return Ok(Self::Hash(Felt::from_hex(hash)?));
+        }
+        if let Some(n) = value["block_number"].as_u64() {
+            return Ok(Self::Number(n));
+        }
+
+        Err(anyhow!("invalid block reference: {}", value))
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Number(n) => json!(n),
+            Self::Hash(hash) => json!({ "block_hash": format!("{:#x}", hash) }),
+            Self::Latest => json!("latest"),
+            Self::Pending => json!("pending"),
+            Self::L1Accepted => json!("l1_accepted"),
+        }
+    }
+
+    pub fn number(&self) -> Option<u64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    // resolves differently as the chain grows
+    pub fn is_moving(&self) -> bool {
+        matches!(self, Self::Latest | Self::Pending | Self::L1Accepted)
+    }
+
+    // as a starknet_getEvents filter end; the typed BlockId has no
+    // l1_accepted, and the pending block is named as the spec version has it
+    pub fn to_request_json(&self, spec_version: Option<SpecVersion>) -> serde_json::Value {
+        match self {
+            Self::Number(n) => json!({ "block_number": n }),
+            Self::Pending => json!(spec_version.map_or("pending", |v| v.pending_tag())),
+            _ => self.to_json(),
+        }
+    }
+
+    pub fn to_confirmed_block_id(&self) -> eyre::Result<ConfirmedBlockId> {
+        match self {
+            Self::Number(n) => Ok(ConfirmedBlockId::Number(*n)),
+            Self::Hash(hash) => Ok(ConfirmedBlockId::Hash(*hash)),
+            Self::Latest => Ok(ConfirmedBlockId::Latest),
+            _ => Err(anyhow!("{} can't start a subscription", self)),
+        }
+    }
+}
+
+impl From<BlockId> for BlockRef {
+    fn from(block_id: BlockId) -> Self {
+        match block_id {
+            BlockId::Number(n) => Self::Number(n),
+            BlockId::Hash(hash) => Self::Hash(hash),
+            BlockId::Tag(BlockTag::Latest) => Self::Latest,
+            BlockId::Tag(BlockTag::Pending) => Self::Pending,
+        }
+    }
+}
+
+impl fmt::Display for BlockRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Hash(hash) => write!(f, "{:#x}", hash),
+            Self::Latest => write!(f, "latest"),
+            Self::Pending => write!(f, "pending"),
+            Self::L1Accepted => write!(f, "l1_accepted"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use super::BlockRef;
+    use crate::spec::SpecVersion;
+
+    #[test]
+    fn parses_numbers_tags_and_hashes() {
+        assert_eq!(BlockRef::from_json(&json!(7)).unwrap(), BlockRef::Number(7));
+        assert_eq!(
+            BlockRef::from_json(&json!({ "block_number": 7 })).unwrap(),
+            BlockRef::Number(7)
+        );
+        assert_eq!(
+            BlockRef::from_json(&json!({ "block_hash": "0x1f" })).unwrap(),
+            BlockRef::Hash(Felt::from(0x1f_u64))
+        );
+        assert_eq!(
+            BlockRef::from_json(&json!("latest")).unwrap(),
+            BlockRef::Latest
+        );
+        assert_eq!(
+            BlockRef::from_json(&json!("l1_accepted")).unwrap(),
+            BlockRef::L1Accepted
+        );
+    }
+
+    #[test]
+    fn pre_confirmed_is_pending() {
+        for tag in ["pending", "pre_confirmed"] {
+            assert_eq!(BlockRef::from_json(&json!(tag)).unwrap(), BlockRef::Pending);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_references() {
+        for value in [
+            json!("earliest"),
+            json!(-1),
+            json!({ "block_hash": "0xzz" }),
+            json!({}),
+        ] {
+            assert!(BlockRef::from_json(&value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn json_round_trips() {
+        for block in [
+            BlockRef::Number(3),
+            BlockRef::Hash(Felt::TWO),
+            BlockRef::Latest,
+            BlockRef::Pending,
+            BlockRef::L1Accepted,
+        ] {
+            assert_eq!(BlockRef::from_json(&block.to_json()).unwrap(), block);
+        }
+    }
+
+    #[test]
+    fn only_tags_are_moving() {
+        assert!(!BlockRef::Number(3).is_moving());
+        assert!(!BlockRef::Hash(Felt::ONE).is_moving());
+        assert!(BlockRef::Latest.is_moving());
+        assert!(BlockRef::Pending.is_moving());
+        assert!(BlockRef::Pending.to_confirmed_block_id().is_err());
+    }
+
+    #[test]
+    fn request_ends_use_rpc_spelling() {
+        let v = SpecVersion::parse("0.9").ok();
+        assert_eq!(
+            BlockRef::Number(3).to_request_json(v),
+            json!({ "block_number": 3 })
+        );
+        assert_eq!(
+            BlockRef::Hash(Felt::TWO).to_request_json(v),
+            json!({ "block_hash": "0x2" })
+        );
+        assert_eq!(BlockRef::Latest.to_request_json(v), json!("latest"));
+        assert_eq!(BlockRef::Pending.to_request_json(v), json!("pre_confirmed"));
+        assert_eq!(BlockRef::Pending.to_request_json(None), json!("pending"));
+        assert_eq!(
+            BlockRef::L1Accepted.to_request_json(v),
+            json!("l1_accepted")
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 
 use std::path::PathBuf;
 
@@ -19,6 +19,12 @@ pub struct Cli {
         default_value = "ws://localhost:9545/rpc/v0_8"
     )]
     pub pathfinder_ws_url: String,
+    #[arg(
+        long,
+        value_name = "version",
+        long_help = "RPC spec version to test (0.7, 0.8, 0.9, ...); appends rpc/vX_Y to the path of both server URLs, in place of an rpc or rpc/vX_Y ending they have, and is checked against starknet_specVersion"
+    )]
+    pub spec_version: Option<String>,
     #[arg(
         long,
         value_name = "fixtures",
@@ -33,4 +39,198 @@ pub struct Cli {
         default_value = "false"
     )]
     pub subscribe: bool,
+    #[arg(
+        long,
+        long_help = "Derive expected events of filtered fixtures from their unfiltered range",
+        default_value = "false"
+    )]
+    pub derive: bool,
+    #[arg(
+        long,
+        value_name = "sizes",
+        long_help = "Comma-separated page sizes to fetch every fixture with; each must yield the same events in ceil(n/size) pages; a manifest case's own page_sizes replace them",
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value = "1024"
+    )]
+    pub page_sizes: Vec<u64>,
+    #[arg(
+        long,
+        long_help = "Resume every fixture from each of its continuation tokens, also checking the tokens are stable and bound to their filter; fixtures are split into a few pages for this regardless of --page-sizes; ranges ending at a moving tag are skipped",
+        default_value = "false"
+    )]
+    pub check_tokens: bool,
+    #[arg(
+        long,
+        value_name = "profile",
+        long_help = "Event normalization profile: a spec version with a built-in profile (0.7, 0.8, 0.9, also with a patch version like 0.8.1) or a JSON file with drop, require, felt_fields and canonical_felts; later spec versions use the newest built-in profile [default: the given or reported spec version, or 0.8]"
+    )]
+    pub normalization: Option<String>,
+    #[arg(
+        long,
+        long_help = "Also require felts in getEvents responses to use the spec's canonical hex encoding (rpc mode only)",
+        default_value = "false"
+    )]
+    pub strict_felts: bool,
+    #[arg(
+        long,
+        value_name = "secs",
+        long_help = "Subscribe mode: fail a fixture when no message arrives for this long",
+        default_value = "30"
+    )]
+    pub message_timeout: u64,
+    #[arg(
+        long,
+        value_name = "secs",
+        long_help = "Subscribe mode: fail a fixture that hasn't received all its events after this long",
+        default_value = "300"
+    )]
+    pub fixture_timeout: u64,
+    #[arg(
+        long,
+        value_name = "secs",
+        long_help = "Subscribe mode: how long a fixture keeps listening for events of its range once a new head past the range arrived, to catch events still in flight and to prove empty results. Heads and events come on separate subscriptions, so this is a timing heuristic: events the server sends later than this are missed, and reported as missing or not as extra; raise it for slow or distant servers",
+        default_value = "1"
+    )]
+    pub settle_time: u64,
+    #[arg(
+        long,
+        value_name = "secs",
+        long_help = "Subscribe mode: timeout of the WebSocket handshake",
+        default_value = "5"
+    )]
+    pub connect_timeout: u64,
+    #[arg(
+        long,
+        long_help = "Subscribe mode: open a connection per fixture instead of sharing one, which concurrent jobs subscribe on together",
+        default_value = "false"
+    )]
+    pub connection_per_fixture: bool,
+    #[arg(
+        long,
+        value_name = "n",
+        long_help = "Subscribe mode: subscribe to up to n fixtures at once on the shared connection before reading any, checking every subscription gets exactly its own fixture's events in order; fixtures are batched only with others whose filters no event can match both of, the rest run alone",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        conflicts_with_all = ["connection_per_fixture", "jobs"]
+    )]
+    pub demux: Option<usize>,
+    #[arg(
+        long,
+        short = 'j',
+        value_name = "n",
+        long_help = "Number of fixtures checked concurrently",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        default_value = "1"
+    )]
+    pub jobs: usize,
+    #[arg(
+        long,
+        value_name = "glob",
+        long_help = "Only run cases whose name matches the glob, e.g. 0+1000* (repeatable)"
+    )]
+    pub include: Vec<String>,
+    #[arg(
+        long,
+        value_name = "glob",
+        long_help = "Skip cases whose name matches the glob (repeatable)"
+    )]
+    pub exclude: Vec<String>,
+    #[arg(
+        long,
+        value_name = "from..to",
+        long_help = "Only run cases whose block range intersects the inclusive range, e.g. 800000..900000; either end may be left open"
+    )]
+    pub blocks: Option<String>,
+    #[arg(
+        long,
+        long_help = "Only run cases with an address or keys filter",
+        default_value = "false",
+        conflicts_with = "unfiltered_only"
+    )]
+    pub filtered_only: bool,
+    #[arg(
+        long,
+        long_help = "Only run cases without an address or keys filter",
+        default_value = "false"
+    )]
+    pub unfiltered_only: bool,
+    #[arg(
+        long,
+        value_name = "i/n",
+        long_help = "Only run the i-th of n deterministic shards of the selected cases, counting from 1"
+    )]
+    pub shard: Option<String>,
+    #[arg(
+        long,
+        value_name = "path",
+        long_help = "Write a JUnit XML report of the run"
+    )]
+    pub report_junit: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "path",
+        long_help = "Write a JSON report of the run"
+    )]
+    pub report_json: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Record a fixture from the server into the fixture directory")]
+    Record(RecordArgs),
+    #[command(
+        about = "Compare stored filtered fixtures with ones derived from their unfiltered range"
+    )]
+    VerifyDerived,
+    #[command(
+        about = "Write the fixture directory manifest, converting fixtures known by legacy names"
+    )]
+    WriteManifest,
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    #[arg(long, value_name = "n", long_help = "First block of the range")]
+    pub from_block: u64,
+    #[arg(
+        long,
+        value_name = "n",
+        long_help = "Number of blocks following the first block",
+        default_value = "0"
+    )]
+    pub block_count: u64,
+    #[arg(long, value_name = "felt", long_help = "Contract address filter")]
+    pub address: Option<String>,
+    #[arg(
+        long,
+        value_name = "json",
+        long_help = "Keys filter, as JSON array of alternatives per position"
+    )]
+    pub keys: Option<String>,
+    #[arg(
+        long,
+        value_name = "name",
+        long_help = "Filter name (defaults to the next free number for the range)"
+    )]
+    pub name: Option<String>,
+    #[arg(
+        long,
+        value_name = "text",
+        long_help = "Case description, kept when the fixture directory has a manifest"
+    )]
+    pub description: Option<String>,
+    #[arg(
+        long,
+        value_name = "version",
+        long_help = "Version of the node serving the events, stored in the directory metadata unless it has one"
+    )]
+    pub node_version: Option<String>,
+    #[arg(
+        long,
+        long_help = "Overwrite the fixture and filter files if they exist",
+        default_value = "false"
+    )]
+    pub force: bool,
 }
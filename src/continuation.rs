@@ -0,0 +1,208 @@
+use starknet::{
+    core::types::Felt,
+    providers::jsonrpc::{HttpTransport, JsonRpcResponse},
+};
+
+use crate::error_case::INVALID_CONTINUATION_TOKEN;
+use crate::get_events::{self, Filter, fetch_page};
+use crate::outcome::Violation;
+use crate::spec::SpecVersion;
+
+// pages a fixture is split into for the check, enough for tokens in the
+// middle of the range and one resuming from the last page
+pub const TOKEN_CHECK_PAGES: u64 = 4;
+
+struct Page {
+    token: Option<String>,
+    events: Vec<serde_json::Value>,
+    next_token: Option<String>,
+}
+
+// re-issues the query from every intermediate continuation token, checking
+// that the tail is the same, that the token gives the same page when
+// repeated, and that it isn't accepted with a different filter
+pub async fn check_continuation_tokens(
+    transport: &HttpTransport,
+    filter: &Filter,
+    page_size: u64,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<()> {
+    let pages = fetch_pages(transport, filter, page_size, None, spec_version).await?;
+    for (i, page) in pages.iter().enumerate().skip(1) {
+        let token = page.token.clone();
+        let tail = fetch_pages(transport, filter, page_size, token.clone(), spec_version).await?;
+        let first = &tail[0];
+        if first.events != page.events || first.next_token != page.next_token {
+            return Err(Violation(format!(
+                "page {} differs when its token {:?} is repeated",
+                i + 1,
+                token
+            ))
+            .into());
+        }
+
+        let expected: Vec<&serde_json::Value> = pages[i..].iter().flat_map(|p| &p.events).collect();
+        let actual: Vec<&serde_json::Value> = tail.iter().flat_map(|p| &p.events).collect();
+        if expected != actual {
+            return Err(Violation(format!(
+                "resuming from token {:?} returned {} events, expected {}",
+                token,
+                actual.len(),
+                expected.len()
+            ))
+            .into());
+        }
+    }
+
+    if let Some(page) = pages.get(1) {
+        let token = page.token.clone();
+        let mut other_filter = filter.clone();
+        other_filter.address = match filter.address {
+            Some(_) => None,
+            None => Some(Felt::ONE),
+        };
+        let request = get_events::request(&other_filter, token.clone(), page_size, spec_version)?;
+        match get_events::send(transport, request).await? {
+            JsonRpcResponse::Error { error, .. } if error.code == INVALID_CONTINUATION_TOKEN => {}
+            JsonRpcResponse::Success { .. } => {
+                return Err(Violation(format!(
+                    "token {:?} accepted with a different filter",
+                    token
+                ))
+                .into());
+            }
+            JsonRpcResponse::Error { error, .. } => {
+                return Err(Violation(format!(
+                    "token {:?} with a different filter: expected INVALID_CONTINUATION_TOKEN, got {} ({})",
+                    token, error.message, error.code
+                ))
+                .into());
+            }
+        }
+    } else {
+        tracing::debug!("single page, no continuation tokens to check");
+    }
+
+    Ok(())
+}
+
+async fn fetch_pages(
+    transport: &HttpTransport,
+    filter: &Filter,
+    page_size: u64,
+    mut token: Option<String>,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<Vec<Page>> {
+    let mut pages = Vec::new();
+    loop {
+        let mut page =
+            fetch_page(transport, filter, token.clone(), page_size, spec_version).await?;
+        let serde_json::Value::Array(events) = page["events"].take() else {
+            return Err(Violation(format!("page w/o events: {}", page)).into());
+        };
+        let next_token = page["continuation_token"].as_str().map(|s| s.to_string());
+        pages.push(Page {
+            token,
+            events,
+            next_token: next_token.clone(),
+        });
+        if next_token.is_none() {
+            break;
+        }
+
+        token = next_token;
+    }
+
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::{
+        core::types::Felt,
+        providers::jsonrpc::{HttpTransport, JsonRpcResponse},
+    };
+
+    use std::fs;
+    use std::path::Path;
+
+    use super::check_continuation_tokens;
+    use crate::block_ref::BlockRef;
+    use crate::error_case::INVALID_CONTINUATION_TOKEN;
+    use crate::fixture_set::FixtureSet;
+    use crate::get_events::{self, Filter, fetch_page};
+    use crate::mock_rpc::{Server, spawn};
+
+    // five events in blocks 10 to 12
+    async fn start_mock(fixture_dir: &Path) -> HttpTransport {
+        let events: Vec<String> = [10, 10, 11, 12, 12]
+            .iter()
+            .enumerate()
+            .map(|(i, block_number)| {
+                json!({
+                    "block_number": block_number,
+                    "from_address": "0x1",
+                    "keys": [format!("{:#x}", i)],
+                    "data": [],
+                    "transaction_hash": "0x2"
+                })
+                .to_string()
+            })
+            .collect();
+        fs::write(fixture_dir.join("10+2.jsonl"), events.join("\n")).unwrap();
+        let url = spawn(Server {
+            fixtures: FixtureSet::load(fixture_dir).unwrap(),
+            max_chunk_size: 1024,
+            max_keys: 16,
+            spec_version: "0.8.1".to_string(),
+            chain_id: Felt::ONE,
+            head: 12,
+        })
+        .await
+        .unwrap();
+        HttpTransport::new(url)
+    }
+
+    fn filter() -> Filter {
+        Filter {
+            from_block: BlockRef::Number(10),
+            to_block: BlockRef::Number(12),
+            address: None,
+            keys: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_from_every_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = start_mock(dir.path()).await;
+        // uneven, even and single pages
+        for page_size in [1, 2, 5] {
+            check_continuation_tokens(&transport, &filter(), page_size, None)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn token_is_rejected_with_another_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = start_mock(dir.path()).await;
+        let page = fetch_page(&transport, &filter(), None, 2, None)
+            .await
+            .unwrap();
+        let token = page["continuation_token"].as_str().map(|s| s.to_string());
+        assert!(token.is_some());
+
+        let mut other_filter = filter();
+        other_filter.address = Some(Felt::ONE);
+        let request = get_events::request(&other_filter, token, 2, None).unwrap();
+        match get_events::send(&transport, request).await.unwrap() {
+            JsonRpcResponse::Error { error, .. } => {
+                assert_eq!(error.code, INVALID_CONTINUATION_TOKEN)
+            }
+            JsonRpcResponse::Success { .. } => panic!("token accepted with another filter"),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use eyre::anyhow;
+use regex::Regex;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::fixture_set::load_events;
+use crate::manifest::Case;
+
+// all fixtures in the directory, including filtered ones that only have a
+// filter file and are expected to be derived from their unfiltered range;
+// other JSON files, like the manifest or block hashes, aren't filters
+pub fn list_fixtures(fixture_dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let filter_rx = Regex::new("^[0-9]+(?:[+][0-9]+)?f[^.]+[.]json$").unwrap();
+    let mut fixtures = BTreeSet::new();
+    for (mask, is_filter) in [("*.jsonl", false), ("*f*.json", true)] {
+        let mask_path = fixture_dir.join(mask);
+        let path_str = mask_path
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid fixture dir: {:?}", fixture_dir))?;
+        for entry in glob::glob(path_str)? {
+            let path = entry?;
+            if is_filter {
+                let name = path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default();
+                if !filter_rx.is_match(name) {
+                    continue;
+                }
+
+                fixtures.insert(filtered_fixture_path(&path)?);
+            } else {
+                fixtures.insert(path);
+            }
+        }
+    }
+
+    Ok(fixtures.into_iter().collect())
+}
+
+// None when the case isn't filtered or its unfiltered range isn't stored
+pub fn derive_events(case: &Case) -> eyre::Result<Option<Vec<serde_json::Value>>> {
+    let (Some(base), Some(matcher)) = (&case.base, case.matcher()) else {
+        return Ok(None);
+    };
+
+    let mut events = Vec::new();
+    for event in load_events(base)? {
+        if matcher.is_match(&event)? {
+            events.push(event);
+        }
+    }
+
+    Ok(Some(events))
+}
+
+pub fn open_expected(case: &Case, derive: bool) -> eyre::Result<fs::File> {
+    if derive && let Some(events) = derive_events(case)? {
+        let mut expected = tempfile::tempfile()?;
+        for event in events {
+            writeln!(&mut expected, "{}", event)?;
+        }
+
+        expected.seek(SeekFrom::Start(0))?;
+        return Ok(expected);
+    }
+
+    Ok(fs::File::open(&case.fixture)?)
+}
+
+pub fn filtered_fixture_path(filter_path: &Path) -> eyre::Result<PathBuf> {
+    let stem = filter_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow!("invalid filter name: {:?}", filter_path))?;
+    let fixture_stem = stem.replacen('f', "w", 1);
+    Ok(filter_path.with_file_name(format!("{}.jsonl", fixture_stem)))
+}
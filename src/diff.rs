@@ -0,0 +1,318 @@
+use eyre::anyhow;
+use serde_json::json;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::felt::{canonical, values_equal};
+
+const MAX_RENDERED: usize = 20;
+
+// events carry no index of their own, so position counts the events of the
+// same transaction seen before this one
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EventKey {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub position: usize,
+}
+
+impl fmt::Display for EventKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} tx {} #{}",
+            self.block_number, self.transaction_hash, self.position
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldChange {
+    pub field: String,
+    pub expected: Option<serde_json::Value>,
+    pub actual: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Diff {
+    pub missing: Vec<(EventKey, serde_json::Value)>,
+    pub unexpected: Vec<(EventKey, serde_json::Value)>,
+    pub changed: Vec<(EventKey, Vec<FieldChange>)>,
+    // same events, different order; index of the first displaced event
+    pub reordered_at: Option<usize>,
+}
+
+impl Diff {
+    pub fn compute(
+        expected: &[serde_json::Value],
+        actual: &[serde_json::Value],
+    ) -> eyre::Result<Self> {
+        let expected_keys = assign_keys(expected)?;
+        let actual_keys = assign_keys(actual)?;
+        let actual_map: HashMap<&EventKey, &serde_json::Value> =
+            actual_keys.iter().zip(actual.iter()).collect();
+        let expected_map: HashMap<&EventKey, &serde_json::Value> =
+            expected_keys.iter().zip(expected.iter()).collect();
+
+        let mut diff = Self::default();
+        for (key, expected_event) in expected_keys.iter().zip(expected.iter()) {
+            match actual_map.get(key) {
+                Some(actual_event) => {
+                    let changes = compare_fields(expected_event, actual_event);
+                    if !changes.is_empty() {
+                        diff.changed.push((key.clone(), changes));
+                    }
+                }
+                None => diff.missing.push((key.clone(), expected_event.clone())),
+            }
+        }
+
+        for (key, actual_event) in actual_keys.iter().zip(actual.iter()) {
+            if !expected_map.contains_key(key) {
+                diff.unexpected.push((key.clone(), actual_event.clone()));
+            }
+        }
+
+        if diff.is_empty() {
+            diff.reordered_at = expected_keys
+                .iter()
+                .zip(actual_keys.iter())
+                .position(|(e, a)| e != a);
+        }
+
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+            && self.unexpected.is_empty()
+            && self.changed.is_empty()
+            && self.reordered_at.is_none()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let key_json = |key: &EventKey| {
+            json!({
+                "block_number": key.block_number,
+                "transaction_hash": key.transaction_hash,
+                "position": key.position,
+            })
+        };
+        let with_event = |(key, event): &(EventKey, serde_json::Value)| {
+            let mut v = key_json(key);
+            v["event"] = event.clone();
+            v
+        };
+        let changed: Vec<serde_json::Value> = self
+            .changed
+            .iter()
+            .map(|(key, changes)| {
+                let mut fields = serde_json::Map::new();
+                for change in changes {
+                    fields.insert(
+                        change.field.clone(),
+                        json!({ "expected": change.expected, "actual": change.actual }),
+                    );
+                }
+                let mut v = key_json(key);
+                v["fields"] = serde_json::Value::Object(fields);
+                v
+            })
+            .collect();
+        json!({
+            "missing": self.missing.iter().map(with_event).collect::<Vec<_>>(),
+            "unexpected": self.unexpected.iter().map(with_event).collect::<Vec<_>>(),
+            "changed": changed,
+            "reordered_at": self.reordered_at,
+        })
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} missing, {} unexpected, {} changed",
+            self.missing.len(),
+            self.unexpected.len(),
+            self.changed.len()
+        )?;
+        if let Some(index) = self.reordered_at {
+            write!(f, ", out of order from event {}", index + 1)?;
+        }
+
+        let mut rendered = 0;
+        for (key, event) in self.missing.iter() {
+            if rendered == MAX_RENDERED {
+                break;
+            }
+            write!(f, "\n  missing    {}: {}", key, event)?;
+            rendered += 1;
+        }
+        for (key, event) in self.unexpected.iter() {
+            if rendered == MAX_RENDERED {
+                break;
+            }
+            write!(f, "\n  unexpected {}: {}", key, event)?;
+            rendered += 1;
+        }
+        for (key, changes) in self.changed.iter() {
+            if rendered == MAX_RENDERED {
+                break;
+            }
+            write!(f, "\n  changed    {}:", key)?;
+            for change in changes {
+                write!(
+                    f,
+                    "\n    {}: expected {}, got {}",
+                    change.field,
+                    show(&change.expected),
+                    show(&change.actual)
+                )?;
+            }
+            rendered += 1;
+        }
+
+        let total = self.missing.len() + self.unexpected.len() + self.changed.len();
+        if total > rendered {
+            write!(f, "\n  ... and {} more", total - rendered)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diff {}
+
+fn assign_keys(events: &[serde_json::Value]) -> eyre::Result<Vec<EventKey>> {
+    let mut seen: HashMap<(u64, String), usize> = HashMap::new();
+    let mut keys = Vec::with_capacity(events.len());
+    for event in events {
+        let block_number = event["block_number"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("event w/o block number"))?;
+        let transaction_hash = canonical(
+            event["transaction_hash"]
+                .as_str()
+                .ok_or_else(|| anyhow!("event w/o transaction hash"))?,
+        )?;
+        let counter = seen
+            .entry((block_number, transaction_hash.clone()))
+            .or_insert(0);
+        keys.push(EventKey {
+            block_number,
+            transaction_hash,
+            position: *counter,
+        });
+        *counter += 1;
+    }
+
+    Ok(keys)
+}
+
+fn compare_fields(expected: &serde_json::Value, actual: &serde_json::Value) -> Vec<FieldChange> {
+    let (Some(expected_map), Some(actual_map)) = (expected.as_object(), actual.as_object()) else {
+        return if expected == actual {
+            Vec::new()
+        } else {
+            vec![FieldChange {
+                field: String::new(),
+                expected: Some(expected.clone()),
+                actual: Some(actual.clone()),
+            }]
+        };
+    };
+
+    let fields: BTreeSet<&String> = expected_map.keys().chain(actual_map.keys()).collect();
+    fields
+        .into_iter()
+        .filter(
+            |field| match (expected_map.get(*field), actual_map.get(*field)) {
+                (Some(e), Some(a)) => !values_equal(field, e, a),
+                (e, a) => e != a,
+            },
+        )
+        .map(|field| FieldChange {
+            field: field.clone(),
+            expected: expected_map.get(field).cloned(),
+            actual: actual_map.get(field).cloned(),
+        })
+        .collect()
+}
+
+fn show(v: &Option<serde_json::Value>) -> String {
+    match v {
+        Some(v) => v.to_string(),
+        None => "nothing".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Diff;
+
+    fn event(block_number: u64, transaction_hash: &str, data: &[&str]) -> serde_json::Value {
+        json!({
+            "block_number": block_number,
+            "transaction_hash": transaction_hash,
+            "from_address": "0x1",
+            "keys": [],
+            "data": data
+        })
+    }
+
+    #[test]
+    fn equal_events_make_an_empty_diff() {
+        let events = vec![event(1, "0xa", &["0x1"]), event(2, "0xb", &[])];
+        assert!(Diff::compute(&events, &events).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keys_by_block_and_transaction() {
+        let expected = vec![event(1, "0xa", &[]), event(2, "0xb", &[])];
+        let actual = vec![event(1, "0xa", &[]), event(3, "0xb", &[])];
+        let diff = Diff::compute(&expected, &actual).unwrap();
+        assert_eq!(diff.missing.len(), 1);
+        assert_eq!(diff.missing[0].0.block_number, 2);
+        assert_eq!(diff.unexpected.len(), 1);
+        assert_eq!(diff.unexpected[0].0.block_number, 3);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn position_counts_events_of_the_same_transaction() {
+        let expected = vec![event(1, "0xa", &["0x1"]), event(1, "0xa", &["0x2"])];
+        let actual = vec![event(1, "0xa", &["0x1"]), event(1, "0xa", &["0x3"])];
+        let diff = Diff::compute(&expected, &actual).unwrap();
+        assert!(diff.missing.is_empty());
+        assert!(diff.unexpected.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.position, 1);
+        assert_eq!(diff.changed[0].1[0].field, "data");
+    }
+
+    #[test]
+    fn transaction_hashes_are_compared_as_felts() {
+        let expected = vec![event(1, "0xa", &["0x1"])];
+        let actual = vec![event(1, "0x000A", &["0x01"])];
+        assert!(Diff::compute(&expected, &actual).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reordering_is_reported() {
+        let expected = vec![event(1, "0xa", &[]), event(2, "0xb", &[])];
+        let actual = vec![event(2, "0xb", &[]), event(1, "0xa", &[])];
+        let diff = Diff::compute(&expected, &actual).unwrap();
+        assert_eq!(diff.reordered_at, Some(0));
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn events_without_key_fields_are_errors() {
+        let events = vec![json!({ "block_number": 1 })];
+        assert!(Diff::compute(&events, &[]).is_err());
+    }
+}
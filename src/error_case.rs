@@ -0,0 +1,185 @@
+use eyre::anyhow;
+use starknet::providers::jsonrpc::JsonRpcError;
+
+use std::collections::HashMap;
+
+use crate::outcome::Violation;
+
+// starknet_getEvents errors of the spec
+pub const BLOCK_NOT_FOUND: i64 = 24;
+pub const PAGE_SIZE_TOO_BIG: i64 = 31;
+pub const INVALID_CONTINUATION_TOKEN: i64 = 33;
+pub const TOO_MANY_KEYS_IN_FILTER: i64 = 34;
+
+// a filter file declaring "expected_error" describes a getEvents request the
+// server must reject; "chunk_size" and "continuation_token" shape the
+// request, "message" is optional and only checked when given
+pub struct ErrorCase {
+    pub code: i64,
+    pub message: Option<String>,
+    pub chunk_size: Option<u64>,
+    pub continuation_token: Option<String>,
+}
+
+impl ErrorCase {
+    pub fn from_map(filter_map: &HashMap<String, serde_json::Value>) -> eyre::Result<Option<Self>> {
+        let Some(expected_error) = filter_map.get("expected_error") else {
+            return Ok(None);
+        };
+
+        let code = expected_error["code"]
+            .as_i64()
+            .ok_or_else(|| anyhow!("expected error w/o code"))?;
+        let message = expected_error["message"].as_str().map(|s| s.to_string());
+        let chunk_size = match filter_map.get("chunk_size") {
+            Some(v) => Some(v.as_u64().ok_or_else(|| anyhow!("invalid chunk size"))?),
+            None => None,
+        };
+        let continuation_token = match filter_map.get("continuation_token") {
+            Some(v) => Some(
+                v.as_str()
+                    .ok_or_else(|| anyhow!("invalid continuation token"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        Ok(Some(Self {
+            code,
+            message,
+            chunk_size,
+            continuation_token,
+        }))
+    }
+
+    pub fn check(&self, error: &JsonRpcError) -> Result<(), Violation> {
+        if error.code != self.code {
+            return Err(Violation(format!(
+                "expected error {}, got {} ({})",
+                describe_code(self.code),
+                describe_code(error.code),
+                error.message
+            )));
+        }
+
+        match &self.message {
+            Some(message) if *message != error.message => Err(Violation(format!(
+                "expected error message {:?}, got {:?}",
+                message, error.message
+            ))),
+            None if error.message.trim().is_empty() => Err(Violation(format!(
+                "error {} has an empty message",
+                describe_code(error.code)
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn describe_code(code: i64) -> String {
+    let name = match code {
+        BLOCK_NOT_FOUND => "BLOCK_NOT_FOUND",
+        PAGE_SIZE_TOO_BIG => "PAGE_SIZE_TOO_BIG",
+        INVALID_CONTINUATION_TOKEN => "INVALID_CONTINUATION_TOKEN",
+        TOO_MANY_KEYS_IN_FILTER => "TOO_MANY_KEYS_IN_FILTER",
+        _ => return code.to_string(),
+    };
+    format!("{} {}", code, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::providers::jsonrpc::JsonRpcError;
+
+    use std::collections::HashMap;
+
+    use super::ErrorCase;
+
+    fn filter_map(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn error(code: i64, message: &str) -> JsonRpcError {
+        JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    fn error_case(code: i64, message: Option<&str>) -> ErrorCase {
+        ErrorCase {
+            code,
+            message: message.map(|s| s.to_string()),
+            chunk_size: None,
+            continuation_token: None,
+        }
+    }
+
+    #[test]
+    fn parses_expected_error() {
+        let case = ErrorCase::from_map(&filter_map(json!({
+            "expected_error": { "code": 33, "message": "Invalid continuation token" },
+            "chunk_size": 5,
+            "continuation_token": "0-1"
+        })))
+        .unwrap()
+        .unwrap();
+        assert_eq!(case.code, 33);
+        assert_eq!(case.message.as_deref(), Some("Invalid continuation token"));
+        assert_eq!(case.chunk_size, Some(5));
+        assert_eq!(case.continuation_token.as_deref(), Some("0-1"));
+
+        let case = ErrorCase::from_map(&filter_map(json!({ "expected_error": { "code": 24 } })))
+            .unwrap()
+            .unwrap();
+        assert_eq!(case.message, None);
+        assert_eq!(case.chunk_size, None);
+    }
+
+    #[test]
+    fn no_expected_error_is_no_case() {
+        let case = ErrorCase::from_map(&filter_map(json!({ "chunk_size": 5 }))).unwrap();
+        assert!(case.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        for value in [
+            json!({ "expected_error": {} }),
+            json!({ "expected_error": { "code": "33" } }),
+            json!({ "expected_error": { "code": 31 }, "chunk_size": "5" }),
+            json!({ "expected_error": { "code": 31 }, "chunk_size": -1 }),
+            json!({ "expected_error": { "code": 33 }, "continuation_token": 1 }),
+        ] {
+            assert!(
+                ErrorCase::from_map(&filter_map(value.clone())).is_err(),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn checks_code_and_message() {
+        let case = error_case(31, Some("Requested page size is too big"));
+        assert!(
+            case.check(&error(31, "Requested page size is too big"))
+                .is_ok()
+        );
+        let err = case.check(&error(24, "Block not found")).unwrap_err();
+        assert_eq!(
+            err.0,
+            "expected error 31 PAGE_SIZE_TOO_BIG, got 24 BLOCK_NOT_FOUND (Block not found)"
+        );
+        assert!(case.check(&error(31, "Page size too big")).is_err());
+    }
+
+    #[test]
+    fn omitted_message_must_not_be_empty() {
+        let case = error_case(33, None);
+        assert!(case.check(&error(33, "anything")).is_ok());
+        assert!(case.check(&error(33, " ")).is_err());
+        assert!(case.check(&error(99, "unknown")).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use eyre::anyhow;
+use starknet::core::types::Felt;
+
+use crate::outcome::Violation;
+
+pub const FELT_FIELDS: [&str; 4] = ["data", "from_address", "keys", "transaction_hash"];
+
+// felt-typed values are equal when they denote the same felts, however they
+// are spelled; anything else has to match exactly
+pub fn values_equal(field: &str, expected: &serde_json::Value, actual: &serde_json::Value) -> bool {
+    if expected == actual {
+        return true;
+    }
+
+    if !FELT_FIELDS.contains(&field) || expected.is_array() != actual.is_array() {
+        return false;
+    }
+
+    match (parse_felts(expected), parse_felts(actual)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+pub fn canonical(s: &str) -> eyre::Result<String> {
+    Ok(format!("{:#x}", parse_felt(s)?))
+}
+
+// tolerates any number of leading zeros, but not values past the prime
+pub fn parse_felt(s: &str) -> eyre::Result<Felt> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .ok_or_else(|| anyhow!("felt w/o 0x prefix: {}", s))?;
+    if digits.is_empty() {
+        return Err(anyhow!("felt w/o digits: {}", s));
+    }
+
+    let trimmed = match digits.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    let felt = Felt::from_hex(&format!("0x{}", trimmed))?;
+    // from_hex reduces values past the field prime
+    if format!("{:x}", felt) != trimmed.to_ascii_lowercase() {
+        return Err(anyhow!("felt out of range: {}", s));
+    }
+
+    Ok(felt)
+}
+
+// the spec's FELT pattern: no leading zeros, either case
+pub fn is_canonical(s: &str) -> bool {
+    let Some(digits) = s.strip_prefix("0x") else {
+        return false;
+    };
+
+    !digits.is_empty()
+        && digits.len() <= 63
+        && digits.chars().all(|c| c.is_ascii_hexdigit())
+        && (digits == "0" || !digits.starts_with('0'))
+}
+
+pub fn check_encoding(event: &serde_json::Value) -> Result<(), Violation> {
+    for field in FELT_FIELDS {
+        if let Some(value) = event.get(field)
+            && let Some(bad) = find_non_canonical(value)
+        {
+            return Err(Violation(format!(
+                "{} has non-canonical felt {:?}: {}",
+                field, bad, event
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn find_non_canonical(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) if !is_canonical(s) => Some(s.clone()),
+        serde_json::Value::Array(items) => items.iter().find_map(find_non_canonical),
+        _ => None,
+    }
+}
+
+fn parse_felts(value: &serde_json::Value) -> eyre::Result<Vec<Felt>> {
+    match value {
+        serde_json::Value::String(s) => Ok(vec![parse_felt(s)?]),
+        serde_json::Value::Array(items) => {
+            let mut felts = Vec::new();
+            for item in items {
+                let s = item
+                    .as_str()
+                    .ok_or_else(|| anyhow!("unexpected felt type: {}", item))?;
+                felts.push(parse_felt(s)?);
+            }
+
+            Ok(felts)
+        }
+        _ => Err(anyhow!("unexpected felt type: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use super::{canonical, check_encoding, is_canonical, parse_felt, values_equal};
+
+    #[test]
+    fn parses_any_spelling() {
+        for s in ["0x1f", "0x001F", "0X1f"] {
+            assert_eq!(parse_felt(s).unwrap(), Felt::from(0x1f_u64), "{}", s);
+        }
+        assert_eq!(parse_felt("0x000").unwrap(), Felt::ZERO);
+        assert_eq!(canonical("0x00Ab").unwrap(), "0xab");
+    }
+
+    #[test]
+    fn rejects_malformed_felts() {
+        for s in [
+            "",
+            "1f",
+            "0x",
+            "0xg1",
+            "0x 1",
+            "-0x1",
+            // 2^251 + 17 * 2^192 + 1 is the field prime
+            "0x800000000000011000000000000000000000000000000000000000000000001",
+            "0x1000000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            assert!(parse_felt(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn largest_felt_is_prime_minus_one() {
+        let max = parse_felt("0x800000000000011000000000000000000000000000000000000000000000000")
+            .unwrap();
+        assert_eq!(max, Felt::MAX);
+        assert_eq!(max + Felt::ONE, Felt::ZERO);
+    }
+
+    #[test]
+    fn canonical_encoding() {
+        for s in ["0x0", "0x1f", "0xAb"] {
+            assert!(is_canonical(s), "{}", s);
+        }
+        for s in ["0x", "0x01", "0X1", "1", "0x00", "0xg"] {
+            assert!(!is_canonical(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn felt_fields_compare_numerically() {
+        assert!(values_equal(
+            "data",
+            &json!(["0x01", "0xA"]),
+            &json!(["0x1", "0xa"])
+        ));
+        assert!(values_equal(
+            "transaction_hash",
+            &json!("0x0a"),
+            &json!("0xa")
+        ));
+        assert!(!values_equal("data", &json!(["0x1"]), &json!("0x1")));
+        assert!(!values_equal("data", &json!(["0x1"]), &json!(["0x2"])));
+        assert!(!values_equal("block_number", &json!("0x01"), &json!("0x1")));
+    }
+
+    #[test]
+    fn finds_non_canonical_felts() {
+        let event = json!({
+            "from_address": "0x1",
+            "keys": [["0x2"]],
+            "data": ["0x3"],
+            "transaction_hash": "0x4"
+        });
+        assert!(check_encoding(&event).is_ok());
+        let event = json!({ "from_address": "0x1", "data": ["0x3", "0x04"] });
+        assert!(check_encoding(&event).is_err());
+    }
+}
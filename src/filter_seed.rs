@@ -27,58 +27,26 @@ impl FilterSeed {
         &self,
         fixture: &Path,
     ) -> eyre::Result<(Option<Felt>, Option<Vec<Vec<Felt>>>)> {
-        let (raw_address, raw_keys) = if let Some(basename) = self.format_filter_basename() {
+        match self.load_filter_map(fixture)? {
+            Some(filter_map) => parse_filter_map(&filter_map),
+            None => Ok((None, None)),
+        }
+    }
+
+    pub fn load_filter_map(
+        &self,
+        fixture: &Path,
+    ) -> eyre::Result<Option<HashMap<String, serde_json::Value>>> {
+        if let Some(basename) = self.format_filter_basename() {
             let fixture_dir = fixture
                 .parent()
                 .ok_or_else(|| anyhow!("fixture without path: {:?}", fixture))?;
             let filter_path = fixture_dir.join(basename);
             let contents = fs::read_to_string(filter_path)?;
-            let filter_map: HashMap<String, serde_json::Value> = serde_json::from_str(&contents)?;
-            let raw_address =
-                if let Some(serde_json::Value::String(addr)) = filter_map.get("address") {
-                    Some(addr.clone())
-                } else {
-                    None
-                };
-            let raw_keys = if let Some(serde_json::Value::Array(keys)) = filter_map.get("keys") {
-                Some(keys.clone())
-            } else {
-                None
-            };
-            (raw_address, raw_keys)
+            Ok(Some(serde_json::from_str(&contents)?))
         } else {
-            (None, None)
-        };
-
-        let address = match raw_address {
-            Some(s) => Some(Felt::from_hex(&s)?),
-            None => None,
-        };
-
-        let keys = match raw_keys {
-            Some(outer) => {
-                let mut key_filter = Vec::new();
-                for inner in outer.into_iter() {
-                    if let serde_json::Value::Array(arr) = inner {
-                        let mut alt = Vec::new();
-                        for v in arr {
-                            if let serde_json::Value::String(k) = v {
-                                alt.push(Felt::from_hex(&k)?);
-                            } else {
-                                return Err(anyhow!("unexpected key type"));
-                            }
-                        }
-
-                        key_filter.push(alt);
-                    }
-                }
-
-                Some(key_filter)
-            }
-            None => None,
-        };
-
-        Ok((address, keys))
+            Ok(None)
+        }
     }
 
     fn from_stem(stem: &str) -> eyre::Result<Self> {
@@ -108,17 +76,30 @@ impl FilterSeed {
         Ok(ret)
     }
 
-    fn format_filter_basename(&self) -> Option<String> {
+    pub fn format_fixture_basename(&self) -> String {
         if let Some(with_name) = &self.with_name {
-            let head = if self.from_block == self.to_block {
-                self.from_block.to_string()
-            } else {
-                format!("{}+{}", self.from_block, self.to_block - self.from_block)
-            };
+            format!("{}w{}.jsonl", self.format_head(), with_name)
+        } else {
+            format!("{}.jsonl", self.format_head())
+        }
+    }
+
+    pub fn format_filter_basename(&self) -> Option<String> {
+        self.with_name
+            .as_ref()
+            .map(|with_name| format!("{}f{}.json", self.format_head(), with_name))
+    }
+
+    // shared by all fixtures of the range
+    pub fn format_block_hashes_basename(&self) -> String {
+        format!("{}h.json", self.format_head())
+    }
 
-            Some(format!("{}f{}.json", head, with_name))
+    fn format_head(&self) -> String {
+        if self.from_block == self.to_block {
+            self.from_block.to_string()
         } else {
-            None
+            format!("{}+{}", self.from_block, self.to_block - self.from_block)
         }
     }
 
@@ -138,3 +119,55 @@ impl FilterSeed {
         Ok(pair)
     }
 }
+
+#[allow(clippy::type_complexity)]
+pub fn parse_filter(contents: &str) -> eyre::Result<(Option<Felt>, Option<Vec<Vec<Felt>>>)> {
+    let filter_map: HashMap<String, serde_json::Value> = serde_json::from_str(contents)?;
+    parse_filter_map(&filter_map)
+}
+
+#[allow(clippy::type_complexity)]
+pub fn parse_filter_map(
+    filter_map: &HashMap<String, serde_json::Value>,
+) -> eyre::Result<(Option<Felt>, Option<Vec<Vec<Felt>>>)> {
+    let raw_address = if let Some(serde_json::Value::String(addr)) = filter_map.get("address") {
+        Some(addr.clone())
+    } else {
+        None
+    };
+    let raw_keys = if let Some(serde_json::Value::Array(keys)) = filter_map.get("keys") {
+        Some(keys.clone())
+    } else {
+        None
+    };
+
+    let address = match raw_address {
+        Some(s) => Some(Felt::from_hex(&s)?),
+        None => None,
+    };
+
+    let keys = match raw_keys {
+        Some(outer) => {
+            let mut key_filter = Vec::new();
+            for inner in outer.into_iter() {
+                if let serde_json::Value::Array(arr) = inner {
+                    let mut alt = Vec::new();
+                    for v in arr {
+                        if let serde_json::Value::String(k) = v {
+                            alt.push(Felt::from_hex(&k)?);
+                        } else {
+                            return Err(anyhow!("unexpected key type"));
+                        }
+                    }
+
+                    key_filter.push(alt);
+                }
+            }
+
+            Some(key_filter)
+        }
+        None => None,
+    };
+
+    Ok((address, keys))
+}
@@ -0,0 +1,72 @@
+use starknet::core::types::Felt;
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::block_ref::BlockRef;
+use crate::manifest::load_cases;
+use crate::util::parse_event;
+
+pub struct FixtureEntry {
+    pub path: PathBuf,
+    pub from_block: BlockRef,
+    pub to_block: BlockRef,
+    pub address: Option<Felt>,
+    pub keys: Option<Vec<Vec<Felt>>>,
+    pub block_hashes: Option<PathBuf>,
+}
+
+pub struct FixtureSet {
+    pub entries: Vec<FixtureEntry>,
+}
+
+impl FixtureSet {
+    // stored fixtures only; derived and expected-error cases have no events
+    pub fn load(fixture_dir: &Path) -> eyre::Result<Self> {
+        let mut entries = Vec::new();
+        for case in load_cases(fixture_dir)? {
+            if case.error.is_none() && case.fixture.exists() {
+                entries.push(FixtureEntry {
+                    path: case.fixture,
+                    from_block: case.from_block,
+                    to_block: case.to_block,
+                    address: case.address,
+                    keys: case.keys,
+                    block_hashes: case.block_hashes,
+                });
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn find(
+        &self,
+        from_block: &BlockRef,
+        to_block: &BlockRef,
+        address: &Option<Felt>,
+        keys: &Option<Vec<Vec<Felt>>>,
+    ) -> Option<&FixtureEntry> {
+        self.entries.iter().find(|e| {
+            e.from_block == *from_block
+                && e.to_block == *to_block
+                && e.address == *address
+                && e.keys == *keys
+        })
+    }
+}
+
+pub fn load_events(fixture: &Path) -> eyre::Result<Vec<serde_json::Value>> {
+    read_events(fs::File::open(fixture)?)
+}
+
+pub fn read_events(source: fs::File) -> eyre::Result<Vec<serde_json::Value>> {
+    let reader = BufReader::new(source);
+    let mut events = Vec::new();
+    for line in reader.lines() {
+        events.push(parse_event(&line?)?);
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,120 @@
+use eyre::anyhow;
+use starknet::{
+    core::types::{
+        EventFilter, EventFilterWithPage, Felt, ResultPageRequest, requests::GetEventsRequest,
+    },
+    providers::jsonrpc::{HttpTransport, JsonRpcMethod, JsonRpcResponse, JsonRpcTransport},
+};
+
+use crate::block_ref::BlockRef;
+use crate::spec::SpecVersion;
+
+// a starknet_getEvents filter with ends EventFilter can't express, like
+// l1_accepted
+#[derive(Clone)]
+pub struct Filter {
+    pub from_block: BlockRef,
+    pub to_block: BlockRef,
+    pub address: Option<Felt>,
+    pub keys: Option<Vec<Vec<Felt>>>,
+}
+
+// raw starknet_getEvents params; the typed client always spells the pending
+// tag "pending", which spec 0.9 renamed to pre_confirmed
+pub fn request(
+    filter: &Filter,
+    token: Option<String>,
+    chunk_size: u64,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<serde_json::Value> {
+    let mut request = serde_json::to_value(GetEventsRequest {
+        filter: EventFilterWithPage {
+            event_filter: EventFilter {
+                from_block: None,
+                to_block: None,
+                address: filter.address,
+                keys: filter.keys.clone(),
+            },
+            result_page_request: ResultPageRequest {
+                continuation_token: token,
+                chunk_size,
+            },
+        },
+    })?;
+    request["filter"]["from_block"] = filter.from_block.to_request_json(spec_version);
+    request["filter"]["to_block"] = filter.to_block.to_request_json(spec_version);
+
+    Ok(request)
+}
+
+pub async fn send(
+    transport: &HttpTransport,
+    request: serde_json::Value,
+) -> eyre::Result<JsonRpcResponse<serde_json::Value>> {
+    Ok(transport
+        .send_request::<_, serde_json::Value>(JsonRpcMethod::GetEvents, request)
+        .await?)
+}
+
+// the typed client keeps only the fields it knows and re-encodes felts, so
+// events are read from the raw responses
+pub async fn fetch_page(
+    transport: &HttpTransport,
+    filter: &Filter,
+    token: Option<String>,
+    page_size: u64,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<serde_json::Value> {
+    match send(transport, request(filter, token, page_size, spec_version)?).await? {
+        JsonRpcResponse::Success { result, .. } => Ok(result),
+        JsonRpcResponse::Error { error, .. } => Err(anyhow!("{} ({})", error.message, error.code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{Filter, request};
+    use crate::block_ref::BlockRef;
+    use crate::spec::SpecVersion;
+
+    fn filter(from_block: BlockRef, to_block: BlockRef) -> Filter {
+        Filter {
+            from_block,
+            to_block,
+            address: None,
+            keys: None,
+        }
+    }
+
+    #[test]
+    fn pending_tag_follows_spec_version() {
+        let filter = filter(BlockRef::Number(5), BlockRef::Pending);
+        for (spec_version, tag) in [
+            (None, "pending"),
+            (Some("0.8"), "pending"),
+            (Some("0.9"), "pre_confirmed"),
+        ] {
+            let spec_version = spec_version.map(|v| SpecVersion::parse(v).unwrap());
+            let request = request(&filter, None, 10, spec_version).unwrap();
+            assert_eq!(request["filter"]["from_block"]["block_number"], 5);
+            assert_eq!(request["filter"]["to_block"], tag);
+            assert_eq!(request["filter"]["chunk_size"], 10);
+        }
+    }
+
+    #[test]
+    fn l1_accepted_and_filter_pass_through() {
+        let mut filter = filter(BlockRef::L1Accepted, BlockRef::Latest);
+        filter.address = Some(Felt::ONE);
+        filter.keys = Some(vec![vec![Felt::TWO]]);
+        let spec_version = SpecVersion::parse("0.9").ok();
+        let request = request(&filter, Some("3".to_string()), 1, spec_version).unwrap();
+        assert_eq!(request["filter"]["from_block"], "l1_accepted");
+        assert_eq!(request["filter"]["to_block"], "latest");
+        assert_eq!(request["filter"]["address"], "0x1");
+        assert_eq!(request["filter"]["keys"][0][0], "0x2");
+        assert_eq!(request["filter"]["continuation_token"], "3");
+    }
+}
@@ -1,3 +1,22 @@
+pub mod block_hashes;
+pub mod block_ref;
 pub mod config;
+pub mod continuation;
+pub mod derive;
+pub mod diff;
+pub mod error_case;
+pub mod felt;
 pub mod filter_seed;
+pub mod fixture_set;
+pub mod get_events;
+pub mod manifest;
+pub mod matcher;
+pub mod metadata;
+pub mod mock_rpc;
+pub mod mock_ws;
+pub mod normalize;
+pub mod outcome;
+pub mod report;
+pub mod selection;
+pub mod spec;
 pub mod util;
@@ -1,159 +1,1111 @@
 use clap::Parser;
-use eyre::anyhow;
-use pretty_assertions_sorted::assert_eq;
+use eyre::{WrapErr, anyhow};
+use futures_util::{StreamExt, future, stream};
 use serde_json::json;
 use starknet::{
-    core::types::{BlockId, ConfirmedBlockId, EventFilter},
+    core::types::{ConfirmedBlockId, SyncStatusType},
     providers::{
-        Provider, Url,
-        jsonrpc::{HttpTransport, JsonRpcClient},
+        Provider, ProviderError, Url,
+        jsonrpc::{HttpTransport, JsonRpcClient, JsonRpcResponse},
     },
 };
-use starknet_tokio_tungstenite::{EventSubscriptionOptions, EventsUpdate, TungsteniteStream};
+use starknet_tokio_tungstenite::{
+    EventSubscriptionOptions, EventsSubscription, EventsUpdate, NewHeadsUpdate, SubscribeError,
+    SubscriptionReceiveError, TungsteniteStream,
+};
+use tokio::sync::Mutex;
 use tracing_subscriber::filter::LevelFilter;
 
 use std::collections::HashMap;
 use std::fs;
-use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
-use std::path::PathBuf;
-use std::time::Duration;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use starknet_event_query::{
-    config::Cli,
-    filter_seed::FilterSeed,
-    util::{parse_event, start_logger},
+    block_hashes::BlockHashes,
+    block_ref::BlockRef,
+    config::{Cli, Command, RecordArgs},
+    continuation::{TOKEN_CHECK_PAGES, check_continuation_tokens},
+    derive::{derive_events, open_expected},
+    diff::Diff,
+    error_case::ErrorCase,
+    felt::check_encoding,
+    filter_seed::{FilterSeed, parse_filter},
+    fixture_set::{load_events, read_events},
+    get_events::{self, Filter, fetch_page},
+    manifest::{
+        Case, InvalidCase, MANIFEST_FILE, load_cases, load_manifest, read_cases, write_manifest,
+    },
+    matcher::EventMatcher,
+    metadata::{self, METADATA_FILE, Metadata},
+    normalize::Profile,
+    outcome::{FixtureResult, Mode, Stats, Summary, Unchecked, Violation},
+    report::{write_json, write_junit},
+    selection::Selection,
+    spec::SpecVersion,
+    util::start_logger,
 };
 
-fn check_received_data(fixture: PathBuf, mut destination: fs::File) -> eyre::Result<()> {
+fn check_received_data(
+    case: &Case,
+    expected: &[serde_json::Value],
+    mut destination: fs::File,
+) -> eyre::Result<()> {
     destination.seek(SeekFrom::Start(0))?;
-    let actual_reader = BufReader::new(destination);
-    let source = fs::File::open(fixture)?;
-    let expected_reader = BufReader::new(source);
-    for (actual_line, expected_line) in
-        itertools::zip_eq(actual_reader.lines(), expected_reader.lines())
-    {
-        let actual_event = parse_event(&actual_line?)?;
-        let expected_event = parse_event(&expected_line?)?;
-        assert_eq!(actual_event, expected_event);
+    let mut actual = read_events(destination)?;
+    if case.from_block.is_moving() {
+        // nothing stable to compare with, the events only have to match
+        // the filter; few blocks may have none that do
+        if actual.is_empty() {
+            return Err(Unchecked(format!(
+                "no events from {} on, nothing was checked",
+                case.from_block
+            ))
+            .into());
+        }
+
+        // pending events have no block number, but still have to match
+        let matcher = EventMatcher::new(0, u64::MAX, case.address, case.keys.clone());
+        for event in actual.iter() {
+            if !matcher.is_filter_match(event)? {
+                return Err(Violation(format!("event doesn't match the filter: {}", event)).into());
+            }
+        }
+
+        return Ok(());
     }
 
-    Ok(())
+    if case.to_block.is_moving() {
+        // the chain has grown since recording; compare the part it covered
+        let covered_to_block = case
+            .covered_to_block
+            .ok_or_else(|| anyhow!("{} w/o covered_to_block", case.to_block))?;
+        actual.retain(|event| {
+            event["block_number"]
+                .as_u64()
+                .is_some_and(|block_number| block_number <= covered_to_block)
+        });
+    }
+
+    let diff = Diff::compute(expected, &actual)?;
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(diff.into())
+    }
 }
 
-async fn check_rpc_fixture(provider: &impl Provider, fixture: PathBuf) -> eyre::Result<()> {
-    let filter_seed = FilterSeed::load(&fixture)?;
-    let (address, keys) = filter_seed.get_filter_address_and_keys(&fixture)?;
-    let filter = EventFilter {
-        from_block: Some(BlockId::Number(filter_seed.from_block)),
-        to_block: Some(BlockId::Number(filter_seed.to_block)),
-        address,
-        keys,
-    };
+#[allow(clippy::too_many_arguments)]
+async fn fetch_events(
+    transport: &HttpTransport,
+    filter: Filter,
+    page_size: u64,
+    destination: &mut impl Write,
+    block_hashes: &mut BlockHashes,
+    profile: &Profile,
+    strict_felts: bool,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<(usize, usize)> {
     let mut token = None;
-    let mut destination = tempfile::tempfile()?;
     let mut actual_count = 0;
     let mut page_count = 0;
     loop {
-        let page = provider.get_events(filter.clone(), token, 1024).await?;
+        let mut page = fetch_page(transport, &filter, token, page_size, spec_version).await?;
         page_count += 1;
-        for event in page.events {
-            let raw_string = serde_json::to_string(&event)?;
-            let mut event_map: HashMap<String, serde_json::Value> =
-                serde_json::from_str(&raw_string)?;
-            event_map.remove("block_hash");
-            let s = serde_json::to_string(&event_map)?;
-            let v: serde_json::Value = serde_json::from_str(&s)?;
-            writeln!(&mut destination, "{}", v)?;
+        let serde_json::Value::Array(events) = page["events"].take() else {
+            return Err(Violation(format!("page w/o events: {}", page)).into());
+        };
+        for event in events {
+            if strict_felts {
+                check_encoding(&event)?;
+            }
+
+            block_hashes.insert_event(&event, filter.to_block == BlockRef::Pending)?;
+
+            let v = profile.normalize(event)?;
+            writeln!(destination, "{}", v)?;
             actual_count += 1;
         }
 
-        token = page.continuation_token;
+        token = page["continuation_token"].as_str().map(|s| s.to_string());
         if token.is_none() {
             break;
         }
     }
 
-    tracing::debug!("retrieved {} events in {} pages", actual_count, page_count);
-    check_received_data(fixture, destination)
+    Ok((actual_count, page_count))
 }
 
-async fn check_ws_fixture(ws_url: &Url, fixture: PathBuf) -> eyre::Result<()> {
-    let filter_seed = FilterSeed::load(&fixture)?;
-    let (address, keys) = filter_seed.get_filter_address_and_keys(&fixture)?;
-    let stream = TungsteniteStream::connect(ws_url, Duration::from_secs(5))
+async fn check_error_case(
+    transport: &HttpTransport,
+    filter: Filter,
+    case: &ErrorCase,
+    page_size: u64,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<Stats> {
+    let request = get_events::request(
+        &filter,
+        case.continuation_token.clone(),
+        case.chunk_size.unwrap_or(page_size),
+        spec_version,
+    )?;
+    match get_events::send(transport, request).await? {
+        JsonRpcResponse::Success { result, .. } => Err(Violation(format!(
+            "expected error {}, got {} events",
+            case.code,
+            result["events"].as_array().map_or(0, |events| events.len())
+        ))
+        .into()),
+        JsonRpcResponse::Error { error, .. } => {
+            case.check(&error)?;
+            Ok(Stats::default())
+        }
+    }
+}
+
+async fn check_rpc_fixture(
+    transport: &HttpTransport,
+    spec_version: Option<SpecVersion>,
+    case: &Case,
+    cli: &Cli,
+    profile: &Profile,
+    stats: &mut Option<Stats>,
+) -> eyre::Result<()> {
+    let filter = Filter {
+        from_block: case.from_block.clone(),
+        to_block: case.to_block.clone(),
+        address: case.address,
+        keys: case.keys.clone(),
+    };
+    let page_sizes = case.page_sizes.as_deref().unwrap_or(&cli.page_sizes);
+    if let Some(error) = &case.error {
+        let page_size = page_sizes.first().copied().unwrap_or(1024);
+        *stats = Some(check_error_case(transport, filter, error, page_size, spec_version).await?);
+        return Ok(());
+    }
+
+    let expected = if case.from_block.is_moving() {
+        Vec::new()
+    } else {
+        profile.normalize_all(read_events(open_expected(case, cli.derive)?)?)?
+    };
+    let expected_hashes = match &case.block_hashes {
+        Some(path) => Some(BlockHashes::load(path)?),
+        None => None,
+    };
+    for &page_size in page_sizes.iter() {
+        let mut destination = tempfile::tempfile()?;
+        let mut actual_hashes = BlockHashes::default();
+        let (actual_count, page_count) = fetch_events(
+            transport,
+            filter.clone(),
+            page_size,
+            &mut destination,
+            &mut actual_hashes,
+            profile,
+            cli.strict_felts,
+            spec_version,
+        )
         .await
-        .expect("WebSocket connection failed");
-    let mut options = EventSubscriptionOptions::new()
-        .with_block_id(ConfirmedBlockId::Number(filter_seed.from_block));
-    options.from_address = address;
-    options.keys = keys;
-    let mut subscription = stream.subscribe_events(options).await.unwrap();
-    let mut actual_count = 0;
-    let source = fs::File::open(fixture)?;
-    let expected_reader = BufReader::new(source);
-    let mut expected_iter = expected_reader.lines();
-    let Some(expected_res) = expected_iter.next() else {
-        return Err(anyhow!("empty results not supported in subscribe mode"));
+        .wrap_err_with(|| format!("page size {}", page_size))?;
+        stats.get_or_insert(Stats {
+            event_count: actual_count,
+            page_count: Some(page_count),
+        });
+        if let Some(block_hashes) = &expected_hashes {
+            block_hashes
+                .check(&actual_hashes)
+                .wrap_err_with(|| format!("page size {}", page_size))?;
+        }
+
+        tracing::debug!(
+            "retrieved {} events in {} pages of {}",
+            actual_count,
+            page_count,
+            page_size
+        );
+        check_received_data(case, &expected, destination)
+            .wrap_err_with(|| format!("page size {}", page_size))?;
+        // an empty result still takes one page
+        let expected_pages = (actual_count as u64).div_ceil(page_size).max(1);
+        if page_count as u64 != expected_pages {
+            return Err(Violation(format!(
+                "page size {}: {} events came in {} pages, expected {}",
+                page_size, actual_count, page_count, expected_pages
+            ))
+            .into());
+        }
+    }
+
+    // the configured page sizes usually fit a fixture in one page, which
+    // has no token to follow; a range ending at a moving tag can grow
+    // between the pages and the tails compared with them
+    if cli.check_tokens
+        && !case.to_block.is_moving()
+        && let Some(stats) = &stats
+    {
+        let page_size = (stats.event_count as u64)
+            .div_ceil(TOKEN_CHECK_PAGES)
+            .max(1);
+        check_continuation_tokens(transport, &filter, page_size, spec_version)
+            .await
+            .wrap_err_with(|| format!("token check page size {}", page_size))?;
+    }
+
+    if stats.is_none() {
+        return Err(anyhow!("no page size given"));
+    }
+
+    Ok(())
+}
+
+async fn record_fixture(
+    transport: &HttpTransport,
+    provider: &impl Provider,
+    fixture_dir: &Path,
+    args: RecordArgs,
+    profile: &Profile,
+) -> eyre::Result<()> {
+    let to_block = args
+        .from_block
+        .checked_add(args.block_count)
+        .ok_or_else(|| anyhow!("adding block count overflows"))?;
+    let mut filter_map = serde_json::Map::new();
+    if let Some(address) = args.address {
+        filter_map.insert("address".to_string(), json!(address));
+    }
+    if let Some(keys) = args.keys {
+        filter_map.insert("keys".to_string(), serde_json::from_str(&keys)?);
+    }
+
+    let mut filter_seed = FilterSeed {
+        from_block: args.from_block,
+        to_block,
+        with_name: None,
+    };
+    if !filter_map.is_empty() {
+        filter_seed.with_name = match args.name {
+            Some(name) => Some(name),
+            None => Some(next_filter_name(fixture_dir, &filter_seed)),
+        };
+    }
+
+    let filter_json = serde_json::Value::Object(filter_map).to_string();
+    let (address, keys) = parse_filter(&filter_json)?;
+    let filter_path = filter_seed
+        .format_filter_basename()
+        .map(|basename| fixture_dir.join(basename));
+    let fixture = fixture_dir.join(filter_seed.format_fixture_basename());
+    if !args.force {
+        for path in filter_path.iter().chain([&fixture]) {
+            if path.exists() {
+                return Err(anyhow!("{:?} exists, pass --force to overwrite it", path));
+            }
+        }
+    }
+
+    let filter = Filter {
+        from_block: BlockRef::Number(filter_seed.from_block),
+        to_block: BlockRef::Number(filter_seed.to_block),
+        address,
+        keys,
     };
-    let mut expected_line = expected_res?;
+    // nothing in the directory changes until the fetch has succeeded
+    let mut destination = tempfile::NamedTempFile::new_in(fixture_dir)?;
+    let hashes_path = fixture_dir.join(filter_seed.format_block_hashes_basename());
+    let mut block_hashes = if hashes_path.exists() {
+        BlockHashes::load(&hashes_path)?
+    } else {
+        BlockHashes::default()
+    };
+    let (actual_count, page_count) = fetch_events(
+        transport,
+        filter.clone(),
+        1024,
+        &mut destination,
+        &mut block_hashes,
+        profile,
+        false,
+        None,
+    )
+    .await?;
+    destination.persist(&fixture)?;
+    if let Some(filter_path) = &filter_path {
+        let mut filter_file = tempfile::NamedTempFile::new_in(fixture_dir)?;
+        filter_file.write_all(filter_json.as_bytes())?;
+        filter_file.persist(filter_path)?;
+    }
+    block_hashes.save(&hashes_path)?;
+    // fields the directory doesn't have yet are taken from this node
+    let mut metadata = Metadata::load(fixture_dir)?.unwrap_or_default();
+    metadata.fill_missing(Metadata {
+        chain_id: match metadata.chain_id {
+            Some(_) => None,
+            None => Some(provider.chain_id().await?),
+        },
+        spec_version: match metadata.spec_version {
+            Some(_) => None,
+            None => Some(provider.spec_version().await?),
+        },
+        node_version: args.node_version,
+        captured_at: Some(metadata::now()?),
+        ..Default::default()
+    });
+    metadata.extend_range(filter_seed.from_block, filter_seed.to_block);
+    metadata.save(fixture_dir)?;
+    tracing::debug!("updated {:?}", fixture_dir.join(METADATA_FILE));
+
+    tracing::info!(
+        "recorded {} events in {} pages into {:?}",
+        actual_count,
+        page_count,
+        fixture
+    );
+
+    if fixture_dir.join(MANIFEST_FILE).exists() {
+        let mut cases = load_manifest(fixture_dir)?;
+        cases.retain(|case| case.fixture != fixture);
+        let name = filter_seed.format_fixture_basename();
+        cases.push(Case {
+            name: name.trim_end_matches(".jsonl").to_string(),
+            fixture,
+            from_block: BlockRef::Number(filter_seed.from_block),
+            to_block: BlockRef::Number(filter_seed.to_block),
+            covered_to_block: None,
+            address: filter.address,
+            keys: filter.keys,
+            error: None,
+            description: args.description,
+            base: None,
+            block_hashes: Some(hashes_path),
+            page_sizes: None,
+        });
+        write_manifest(fixture_dir, &cases)?;
+    }
+
+    Ok(())
+}
+
+fn next_filter_name(fixture_dir: &Path, filter_seed: &FilterSeed) -> String {
+    let mut filter_no = 1;
     loop {
-        match subscription.recv().await {
-            Ok(EventsUpdate::Event(event)) => {
-                if let Some(block_number) = event.block_number {
-                    if block_number > filter_seed.to_block {
-                        return Err(anyhow!("missing expected values"));
-                    }
+        let candidate = FilterSeed {
+            from_block: filter_seed.from_block,
+            to_block: filter_seed.to_block,
+            with_name: Some(filter_no.to_string()),
+        };
+        if let Some(basename) = candidate.format_filter_basename()
+            && !fixture_dir.join(basename).exists()
+        {
+            return filter_no.to_string();
+        }
+
+        filter_no += 1;
+    }
+}
+
+async fn check_ws_fixture(
+    stream: &TungsteniteStream,
+    case: &Case,
+    cli: &Cli,
+    profile: &Profile,
+    stats: &mut Option<Stats>,
+) -> eyre::Result<()> {
+    let expected = profile.normalize_all(read_events(open_expected(case, cli.derive)?)?)?;
+    let subscription = subscribe_case(stream, case).await?;
+    check_subscription(stream, subscription, case, expected, cli, profile, stats).await
+}
+
+// subscribes the whole batch before reading any of it, so the server has to
+// route every update of the connection to the right subscription
+async fn check_ws_batch(
+    stream: &TungsteniteStream,
+    batch: &[&Case],
+    cli: &Cli,
+    profile: &Profile,
+) -> (Vec<FixtureResult>, bool) {
+    let mut subscribed = Vec::new();
+    for case in batch {
+        let start = Instant::now();
+        let res: eyre::Result<(Vec<serde_json::Value>, EventsSubscription)> = async {
+            let expected = profile.normalize_all(read_events(open_expected(case, cli.derive)?)?)?;
+            Ok((expected, subscribe_case(stream, case).await?))
+        }
+        .await;
+        subscribed.push((start, res));
+    }
+
+    let checks = batch
+        .iter()
+        .zip(subscribed)
+        .map(|(case, (start, res))| async move {
+            let mut stats = None;
+            let res = match res {
+                Ok((expected, subscription)) => {
+                    check_subscription(
+                        stream,
+                        subscription,
+                        case,
+                        expected,
+                        cli,
+                        profile,
+                        &mut stats,
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            let disconnected = res.as_ref().is_err_and(is_disconnect);
+            (
+                FixtureResult::new(case.fixture.clone(), Mode::Ws, start.elapsed(), stats, res),
+                disconnected,
+            )
+        });
+    let (results, disconnected): (Vec<FixtureResult>, Vec<bool>) =
+        future::join_all(checks).await.into_iter().unzip();
+    (results, disconnected.into_iter().any(|d| d))
+}
 
-                    let actual_json = json!({
-                        "block_number": block_number,
-                        "data": event.data,
-                        "from_address": event.from_address,
-                        "keys": event.keys,
-                        "transaction_hash": event.transaction_hash,
-                    });
-                    let expected_json = parse_event(&expected_line)?;
-                    assert_eq!(actual_json, expected_json);
-                    actual_count += 1;
-                    if let Some(expected_res) = expected_iter.next() {
-                        expected_line = expected_res?;
-                    } else {
+async fn subscribe_case(
+    stream: &TungsteniteStream,
+    case: &Case,
+) -> eyre::Result<EventsSubscription> {
+    // a subscription has no end of its own, only a block number past which
+    // the range is complete
+    if case.to_block.number().is_none() {
+        return Err(anyhow!(
+            "subscribe mode needs a numbered to_block, not {}",
+            case.to_block
+        ));
+    }
+
+    let mut options =
+        EventSubscriptionOptions::new().with_block_id(case.from_block.to_confirmed_block_id()?);
+    options.from_address = case.address;
+    options.keys = case.keys.clone();
+    Ok(stream.subscribe_events(options).await?)
+}
+
+async fn check_subscription(
+    stream: &TungsteniteStream,
+    mut subscription: EventsSubscription,
+    case: &Case,
+    expected: Vec<serde_json::Value>,
+    cli: &Cli,
+    profile: &Profile,
+    stats: &mut Option<Stats>,
+) -> eyre::Result<()> {
+    let mut actual = Vec::new();
+    let fixture_timeout = Duration::from_secs(cli.fixture_timeout);
+    let receive = receive_range(
+        stream,
+        &mut subscription,
+        case,
+        expected.len(),
+        &mut actual,
+        cli,
+        profile,
+    );
+    let res = match tokio::time::timeout(fixture_timeout, receive).await {
+        Ok(res) => res,
+        Err(_) => Err(Violation(format!(
+            "timed out after {} of {} expected events: fixture took over {}s",
+            actual.len(),
+            expected.len(),
+            cli.fixture_timeout
+        ))
+        .into()),
+    };
+
+    // whatever went wrong, the server shouldn't keep sending; a server that
+    // doesn't confirm isn't waited for
+    let message_timeout = Duration::from_secs(cli.message_timeout);
+    match tokio::time::timeout(message_timeout, subscription.unsubscribe()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => {
+            if res.is_ok() {
+                return Err(err.into());
+            }
+
+            tracing::warn!("{:?} unsubscribe failed: {}", case.fixture, err);
+        }
+        Err(_) => tracing::warn!(
+            "{:?} unsubscribe not confirmed within {}s",
+            case.fixture,
+            cli.message_timeout
+        ),
+    }
+    *stats = Some(Stats {
+        event_count: actual.len(),
+        page_count: None,
+    });
+    res?;
+
+    // the diff would show them too, but not whose they are
+    if cli.demux.is_some()
+        && let Some(matcher) = case.matcher()
+    {
+        for event in actual.iter() {
+            if !matcher.is_match(event)? {
+                return Err(
+                    Violation(format!("got an event of another subscription: {}", event)).into(),
+                );
+            }
+        }
+    }
+
+    let diff = Diff::compute(&expected, &actual)?;
+    if !diff.is_empty() {
+        return Err(diff.into());
+    }
+
+    tracing::debug!("retrieved {} events", actual.len());
+    Ok(())
+}
+
+// the range is complete with the first event past it, or else a new head
+// past it; heads come on a subscription of their own, so events of the range
+// still in flight get the settle time to show up, and the diff reports
+// whatever doesn't
+async fn receive_range(
+    stream: &TungsteniteStream,
+    subscription: &mut EventsSubscription,
+    case: &Case,
+    expected_count: usize,
+    actual: &mut Vec<serde_json::Value>,
+    cli: &Cli,
+    profile: &Profile,
+) -> eyre::Result<()> {
+    let to_block = case
+        .to_block
+        .number()
+        .ok_or_else(|| anyhow!("subscribe mode needs a numbered to_block"))?;
+    let mut heads = stream
+        .subscribe_new_heads(ConfirmedBlockId::Number(to_block))
+        .await?;
+    let message_timeout = Duration::from_secs(cli.message_timeout);
+    let res = async {
+        loop {
+            tokio::select! {
+                update = subscription.recv() => {
+                    if push_event(update?, case, actual, profile)? {
+                        return Ok(());
+                    }
+                }
+                update = heads.recv() => match update? {
+                    NewHeadsUpdate::NewHeader(header) if header.block_number > to_block => {
                         break;
                     }
-                } else {
-                    return Err(anyhow!("got event w/o block number"));
+                    NewHeadsUpdate::NewHeader(_) => {}
+                    NewHeadsUpdate::Reorg(reorg) => {
+                        return Err(anyhow!(
+                            "encountered reorg {} -> {}",
+                            reorg.starting_block_number,
+                            reorg.ending_block_number
+                        ));
+                    }
+                },
+                _ = tokio::time::sleep(message_timeout) => {
+                    return Err(Violation(format!(
+                        "timed out after {} of {} expected events: no event or head past block {} for {}s",
+                        actual.len(),
+                        expected_count,
+                        to_block,
+                        cli.message_timeout
+                    ))
+                    .into());
                 }
             }
-            Ok(EventsUpdate::Reorg(reorg)) => {
-                // we only test stable historical data
-                return Err(anyhow!(
-                    "encountered reorg {} -> {}",
-                    reorg.starting_block_number,
-                    reorg.ending_block_number
-                ));
+        }
+
+        let settle_time = Duration::from_secs(cli.settle_time);
+        while let Ok(update) = tokio::time::timeout(settle_time, subscription.recv()).await {
+            if push_event(update?, case, actual, profile)? {
+                break;
             }
+        }
+
+        Ok(())
+    }
+    .await;
+
+    match tokio::time::timeout(message_timeout, heads.unsubscribe()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => {
+            tracing::warn!("{:?} unsubscribe from heads failed: {}", case.fixture, err)
+        }
+        Err(_) => tracing::warn!(
+            "{:?} unsubscribe from heads not confirmed within {}s",
+            case.fixture,
+            cli.message_timeout
+        ),
+    }
+    res
+}
+
+// true once the update is past the fixture's range
+fn push_event(
+    update: EventsUpdate,
+    case: &Case,
+    actual: &mut Vec<serde_json::Value>,
+    profile: &Profile,
+) -> eyre::Result<bool> {
+    match update {
+        EventsUpdate::Event(event) => {
+            let Some(block_number) = event.block_number else {
+                return Err(anyhow!("got event w/o block number"));
+            };
+            if let Some(to_block) = case.to_block.number()
+                && block_number > to_block
+            {
+                return Ok(true);
+            }
+
+            actual.push(profile.normalize_typed(&event)?);
+            Ok(false)
+        }
+        EventsUpdate::Reorg(reorg) => {
+            // we only test stable historical data
+            Err(anyhow!(
+                "encountered reorg {} -> {}",
+                reorg.starting_block_number,
+                reorg.ending_block_number
+            ))
+        }
+    }
+}
+
+fn verify_derived(fixture_dir: &Path) -> eyre::Result<()> {
+    let mut checked = 0;
+    let mut disagreements = 0;
+    for case in load_cases(fixture_dir)? {
+        if !case.fixture.exists() {
+            continue;
+        }
+
+        let Some(derived) = derive_events(&case)? else {
+            continue;
+        };
+
+        let stored = load_events(&case.fixture)?;
+        checked += 1;
+        if stored != derived {
+            disagreements += 1;
+            let line = stored
+                .iter()
+                .zip(derived.iter())
+                .position(|(a, b)| a != b)
+                .unwrap_or(stored.len().min(derived.len()))
+                + 1;
+            tracing::warn!(
+                "{:?} has {} events, derived {}, first difference on line {}",
+                case.fixture,
+                stored.len(),
+                derived.len(),
+                line
+            );
+        }
+    }
+
+    tracing::info!(
+        "{} of {} stored filtered fixtures disagree with derived results",
+        disagreements,
+        checked
+    );
+    if disagreements > 0 {
+        Err(anyhow!("stored fixtures disagree with derived results"))
+    } else {
+        Ok(())
+    }
+}
+
+fn list_cases(cli: &Cli) -> eyre::Result<(Vec<Case>, Vec<InvalidCase>)> {
+    let (mut cases, invalid) = read_cases(&cli.fixture_dir)?;
+    // filtered cases need their events stored, or when deriving, their
+    // unfiltered range
+    cases.retain(|case| {
+        case.error.is_some()
+            || case.from_block.is_moving()
+            || case.fixture.exists()
+            || (cli.derive && case.base.is_some())
+    });
+
+    // expected errors are getEvents responses, subscriptions have no
+    // counterpart; a replay ends at a block number, and ranges ending at a
+    // moving tag never finish
+    if cli.subscribe {
+        cases.retain(|case| {
+            case.error.is_none() && !case.from_block.is_moving() && case.to_block.number().is_some()
+        });
+    }
+
+    let count = cases.len();
+    let selection = Selection::from_cli(cli)?;
+    let cases = selection.apply(cases);
+    tracing::info!("selected {} of {} cases", cases.len(), count);
+    let invalid = selection.apply_invalid(invalid);
+    if !invalid.is_empty() {
+        tracing::warn!("{} cases can't be read", invalid.len());
+    }
+
+    Ok((cases, invalid))
+}
+
+async fn run_rpc(
+    rpc_url: Url,
+    spec_version: Option<SpecVersion>,
+    cases: Vec<Case>,
+    cli: &Cli,
+    profile: &Profile,
+) -> Summary {
+    let transport = HttpTransport::new(rpc_url);
+    let mut results = stream::iter(cases)
+        .map(|case| async {
+            let start = Instant::now();
+            let mut stats = None;
+            let res =
+                check_rpc_fixture(&transport, spec_version, &case, cli, profile, &mut stats).await;
+            FixtureResult::new(case.fixture, Mode::Rpc, start.elapsed(), stats, res)
+        })
+        .buffered(cli.jobs);
+    let mut summary = Summary::default();
+    while let Some(result) = results.next().await {
+        summary.record(result);
+    }
+
+    summary
+}
+
+// the connection fixtures share; one the server drops is replaced once
+struct SharedStream<'a> {
+    ws_url: &'a Url,
+    cli: &'a Cli,
+    state: Mutex<SharedState>,
+}
+
+struct SharedState {
+    stream: Arc<TungsteniteStream>,
+    reconnected: bool,
+}
+
+impl<'a> SharedStream<'a> {
+    fn new(ws_url: &'a Url, cli: &'a Cli, stream: TungsteniteStream) -> Self {
+        Self {
+            ws_url,
+            cli,
+            state: Mutex::new(SharedState {
+                stream: Arc::new(stream),
+                reconnected: false,
+            }),
+        }
+    }
+
+    async fn current(&self) -> Arc<TungsteniteStream> {
+        self.state.lock().await.stream.clone()
+    }
+
+    // jobs that saw the same stream drop share one reconnect
+    async fn reconnect(
+        &self,
+        dropped: &Arc<TungsteniteStream>,
+    ) -> eyre::Result<Arc<TungsteniteStream>> {
+        let mut state = self.state.lock().await;
+        if !Arc::ptr_eq(&state.stream, dropped) {
+            return Ok(state.stream.clone());
+        }
+
+        if state.reconnected {
+            return Err(anyhow!("connection closed, already reconnected once"));
+        }
+
+        tracing::warn!("connection to {} closed, reconnecting", self.ws_url);
+        state.reconnected = true;
+        // a restarting server takes a moment to listen again
+        let deadline = Instant::now() + Duration::from_secs(self.cli.connect_timeout);
+        let stream = loop {
+            match connect_ws(self.ws_url, self.cli).await {
+                Ok(stream) => break stream,
+                Err(_) if Instant::now() < deadline => {
+                    tokio::time::sleep(Duration::from_millis(250)).await
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        state.stream = Arc::new(stream);
+        Ok(state.stream.clone())
+    }
+
+    async fn close(self) {
+        let stream = self.state.into_inner().stream;
+        if let Ok(stream) = Arc::try_unwrap(stream)
+            && let Err(err) = stream.close().await
+        {
+            tracing::warn!("closing the connection failed: {}", err);
+        }
+    }
+}
+
+// a batch only holds fixtures no event can belong to two of, so every
+// misrouted event shows; cases that can't tell, like unfiltered ones, go
+// alone
+fn demux_batches(cases: &[Case], width: usize) -> Vec<Vec<&Case>> {
+    let mut batches: Vec<Vec<(&Case, EventMatcher)>> = Vec::new();
+    let mut alone = Vec::new();
+    for case in cases {
+        let Some(matcher) = case.matcher().filter(|_| case.is_filtered()) else {
+            alone.push(vec![case]);
+            continue;
+        };
+
+        match batches.iter_mut().find(|batch| {
+            batch.len() < width && batch.iter().all(|(_, other)| matcher.is_disjoint(other))
+        }) {
+            Some(batch) => batch.push((case, matcher)),
+            None => batches.push(vec![(case, matcher)]),
+        }
+    }
+
+    alone.extend(
+        batches
+            .into_iter()
+            .map(|batch| batch.into_iter().map(|(case, _)| case).collect()),
+    );
+    alone
+}
+
+// the server went away, as opposed to answering wrong
+fn is_disconnect(err: &eyre::Report) -> bool {
+    matches!(
+        err.downcast_ref::<SubscriptionReceiveError>(),
+        Some(SubscriptionReceiveError::StreamClosed)
+    ) || matches!(
+        err.downcast_ref::<SubscribeError>(),
+        Some(SubscribeError::Transport(_))
+    )
+}
+
+async fn run_ws(ws_url: Url, cases: Vec<Case>, cli: &Cli, profile: &Profile) -> Summary {
+    let mut summary = Summary::default();
+    // with more than one job, their subscriptions share the connection
+    let shared = if cli.connection_per_fixture {
+        None
+    } else {
+        match connect_ws(&ws_url, cli).await {
+            Ok(stream) => Some(SharedStream::new(&ws_url, cli, stream)),
             Err(err) => {
-                return Err(err.into());
+                // every fixture still gets its result
+                let message = format!("{:#}", err);
+                for case in cases {
+                    let res = Err(anyhow!("{}", message));
+                    summary.record(FixtureResult::new(
+                        case.fixture,
+                        Mode::Ws,
+                        Duration::ZERO,
+                        None,
+                        res,
+                    ));
+                }
+                return summary;
+            }
+        }
+    };
+    if let Some(width) = cli.demux {
+        let Some(shared) = &shared else {
+            unreachable!("demultiplexing conflicts with --connection-per-fixture");
+        };
+        for batch in demux_batches(&cases, width) {
+            tracing::debug!("subscribing to {} fixtures at once", batch.len());
+            let stream = shared.current().await;
+            let (mut results, disconnected) = check_ws_batch(&stream, &batch, cli, profile).await;
+            if disconnected {
+                match shared.reconnect(&stream).await {
+                    Ok(stream) => {
+                        (results, _) = check_ws_batch(&stream, &batch, cli, profile).await;
+                    }
+                    Err(err) => tracing::error!("{:#}", err),
+                }
+            }
+            for result in results {
+                summary.record(result);
             }
         }
+    } else {
+        let mut results = stream::iter(cases)
+            .map(|case| async {
+                let start = Instant::now();
+                let mut stats = None;
+                let res = match &shared {
+                    Some(shared) => {
+                        let stream = shared.current().await;
+                        let res = check_ws_fixture(&stream, &case, cli, profile, &mut stats).await;
+                        match res {
+                            Err(err) if is_disconnect(&err) => {
+                                match shared.reconnect(&stream).await {
+                                    Ok(stream) => {
+                                        stats = None;
+                                        check_ws_fixture(&stream, &case, cli, profile, &mut stats)
+                                            .await
+                                    }
+                                    Err(reconnect_err) => {
+                                        Err(err.wrap_err(format!("{:#}", reconnect_err)))
+                                    }
+                                }
+                            }
+                            res => res,
+                        }
+                    }
+                    None => {
+                        async {
+                            let stream = connect_ws(&ws_url, cli).await?;
+                            check_ws_fixture(&stream, &case, cli, profile, &mut stats).await
+                        }
+                        .await
+                    }
+                };
+                FixtureResult::new(case.fixture, Mode::Ws, start.elapsed(), stats, res)
+            })
+            .buffered(cli.jobs);
+        while let Some(result) = results.next().await {
+            summary.record(result);
+        }
     }
 
-    subscription.unsubscribe().await?;
-    tracing::debug!("retrieved {} events", actual_count);
-    Ok(())
+    if let Some(shared) = shared {
+        shared.close().await;
+    }
+    summary
+}
+
+async fn connect_ws(ws_url: &Url, cli: &Cli) -> eyre::Result<TungsteniteStream> {
+    TungsteniteStream::connect(ws_url, Duration::from_secs(cli.connect_timeout))
+        .await
+        .wrap_err_with(|| format!("connecting to {}", ws_url))
+}
+
+// a ws-only node has no block number to ask for, but announces its head
+// to a new subscription
+async fn ws_node_head(ws_url: &Url, cli: &Cli) -> eyre::Result<u64> {
+    let stream = connect_ws(ws_url, cli).await?;
+    let message_timeout = Duration::from_secs(cli.message_timeout);
+    let res = async {
+        let mut heads = stream.subscribe_new_heads(ConfirmedBlockId::Latest).await?;
+        let update = tokio::time::timeout(message_timeout, heads.recv())
+            .await
+            .map_err(|_| anyhow!("no head announced for {}s", cli.message_timeout))??;
+        match tokio::time::timeout(message_timeout, heads.unsubscribe()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::warn!("unsubscribe from heads failed: {}", err),
+            Err(_) => tracing::warn!(
+                "unsubscribe from heads not confirmed within {}s",
+                cli.message_timeout
+            ),
+        }
+        match update {
+            NewHeadsUpdate::NewHeader(header) => Ok(header.block_number),
+            NewHeadsUpdate::Reorg(_) => Err(anyhow!("got reorg instead of head")),
+        }
+    }
+    .await;
+    if let Err(err) = stream.close().await {
+        tracing::warn!("closing the connection failed: {}", err);
+    }
+    let head = res?;
+    tracing::info!("node head is block {}", head);
+    Ok(head)
 }
 
-async fn run_rpc(rpc_url: Url, mask_path_str: &str) -> eyre::Result<()> {
-    let provider = JsonRpcClient::new(HttpTransport::new(rpc_url));
-    for entry in glob::glob(mask_path_str)? {
-        check_rpc_fixture(&provider, entry?).await?;
+// the last block the node can answer for
+async fn node_head(provider: &impl Provider) -> eyre::Result<u64> {
+    let head = provider.block_number().await?;
+    match provider.syncing().await? {
+        SyncStatusType::Syncing(status) => tracing::warn!(
+            "node is syncing, at block {} of {}; fixtures past block {} are skipped",
+            status.current_block_num,
+            status.highest_block_num,
+            head
+        ),
+        SyncStatusType::NotSyncing => tracing::info!("node head is block {}", head),
     }
 
+    Ok(head)
+}
+
+async fn check_spec_version(
+    provider: &impl Provider,
+    spec_version: SpecVersion,
+) -> eyre::Result<()> {
+    let reported = provider.spec_version().await?;
+    if SpecVersion::parse(&reported)? != spec_version {
+        return Err(anyhow!(
+            "server reports spec version {}, expected {}",
+            reported,
+            spec_version
+        ));
+    }
+
+    tracing::info!("server speaks spec version {}", reported);
     Ok(())
 }
 
-async fn run_ws(ws_url: Url, mask_path_str: &str) -> eyre::Result<()> {
-    for entry in glob::glob(mask_path_str)? {
-        check_ws_fixture(&ws_url, entry?).await?;
+// the given version, or the one the server reports; tags are spelled and
+// events normalized as that version has them
+async fn server_spec_version(
+    provider: &impl Provider,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<Option<SpecVersion>> {
+    if spec_version.is_some() {
+        return Ok(spec_version);
+    }
+
+    let reported = provider.spec_version().await?;
+    tracing::info!("server speaks spec version {}", reported);
+    Ok(Some(SpecVersion::parse(&reported)?))
+}
+
+fn select_profile(
+    normalization: Option<&str>,
+    spec_version: Option<SpecVersion>,
+) -> eyre::Result<Profile> {
+    match (normalization, spec_version) {
+        (Some(name), _) => Profile::load(name),
+        (None, Some(spec_version)) => Profile::for_spec_version(spec_version),
+        (None, None) => {
+            tracing::warn!("spec version unknown, normalizing events as 0.8");
+            Profile::load("0.8")
+        }
+    }
+}
+
+async fn check_chain(provider: &impl Provider, fixture_dir: &Path) -> eyre::Result<()> {
+    let Some(metadata) = Metadata::load(fixture_dir)? else {
+        tracing::warn!(
+            "no {} in {:?}, network unchecked",
+            METADATA_FILE,
+            fixture_dir
+        );
+        return Ok(());
+    };
+
+    if metadata.chain_id.is_some() {
+        metadata.check_chain_id(provider.chain_id().await?)?;
+    } else {
+        tracing::warn!(
+            "no chain_id in {:?}, network unchecked",
+            fixture_dir.join(METADATA_FILE)
+        );
+    }
+    // patch versions don't change the wire format
+    if let Some(captured_version) = &metadata.spec_version {
+        let spec_version = provider.spec_version().await?;
+        if SpecVersion::parse(captured_version)? != SpecVersion::parse(&spec_version)? {
+            tracing::warn!(
+                "fixtures were captured with spec version {}, server reports {}",
+                captured_version,
+                spec_version
+            );
+        }
     }
 
     Ok(())
@@ -164,15 +1116,487 @@ async fn main() -> eyre::Result<()> {
     start_logger(LevelFilter::INFO);
 
     let cli = Cli::parse();
-    let mask_path = cli.fixture_dir.join("*.jsonl");
-    let path_str = mask_path
-        .to_str()
-        .ok_or_else(|| anyhow!("invalid fixture dir: {:?}", cli.fixture_dir))?;
-    if !cli.subscribe {
-        let rpc_url: Url = cli.pathfinder_rpc_url.parse()?;
-        run_rpc(rpc_url, path_str).await
+    let spec_version = match &cli.spec_version {
+        Some(v) => Some(SpecVersion::parse(v)?),
+        None => None,
+    };
+    let (rpc_url, ws_url): (Url, Url) = match spec_version {
+        Some(spec_version) => (
+            spec_version.endpoint(&cli.pathfinder_rpc_url)?,
+            spec_version.endpoint(&cli.pathfinder_ws_url)?,
+        ),
+        None => (
+            cli.pathfinder_rpc_url.parse()?,
+            cli.pathfinder_ws_url.parse()?,
+        ),
+    };
+    match cli.command {
+        Some(Command::Record(args)) => {
+            let transport = HttpTransport::new(rpc_url);
+            let provider = JsonRpcClient::new(transport.clone());
+            if let Some(spec_version) = spec_version {
+                check_spec_version(&provider, spec_version).await?;
+            }
+            check_chain(&provider, &cli.fixture_dir).await?;
+            let spec_version = server_spec_version(&provider, spec_version).await?;
+            let profile = select_profile(cli.normalization.as_deref(), spec_version)?;
+            return record_fixture(&transport, &provider, &cli.fixture_dir, args, &profile).await;
+        }
+        Some(Command::VerifyDerived) => {
+            return verify_derived(&cli.fixture_dir);
+        }
+        Some(Command::WriteManifest) => {
+            let cases = load_cases(&cli.fixture_dir)?;
+            write_manifest(&cli.fixture_dir, &cases)?;
+            tracing::info!(
+                "wrote {} cases into {:?}",
+                cases.len(),
+                cli.fixture_dir.join(MANIFEST_FILE)
+            );
+            return Ok(());
+        }
+        None => {}
+    }
+
+    if let Some(spec_version) = spec_version
+        && cli.subscribe
+        && !spec_version.supports_subscriptions()
+    {
+        return Err(anyhow!(
+            "spec version {} has no subscriptions",
+            spec_version
+        ));
+    }
+
+    // subscriptions are checked through the HTTP endpoint of the same
+    // server, if it has one
+    let provider = JsonRpcClient::new(HttpTransport::new(rpc_url.clone()));
+    let checked = async {
+        if let Some(spec_version) = spec_version {
+            check_spec_version(&provider, spec_version).await?;
+        }
+        check_chain(&provider, &cli.fixture_dir).await
+    }
+    .await;
+    match checked {
+        Err(err) if cli.subscribe && err.downcast_ref::<ProviderError>().is_some() => {
+            tracing::warn!("spec version and network unchecked: {}", err);
+        }
+        checked => checked?,
+    }
+    let spec_version = match server_spec_version(&provider, spec_version).await {
+        Err(err) if err.downcast_ref::<ProviderError>().is_some() => {
+            tracing::warn!("no spec version, assuming pending is pending: {}", err);
+            None
+        }
+        spec_version => spec_version?,
+    };
+    let profile = select_profile(cli.normalization.as_deref(), spec_version)?;
+    // a ws server that can't be reached fails every fixture below, in the
+    // reports
+    let head = if cli.subscribe {
+        match ws_node_head(&ws_url, &cli).await {
+            Ok(head) => Some(head),
+            Err(err) => {
+                tracing::warn!("node head unknown, no fixtures skipped: {:#}", err);
+                None
+            }
+        }
+    } else {
+        Some(node_head(&provider).await?)
+    };
+
+    // expected errors don't need the node synced, e.g. BLOCK_NOT_FOUND
+    let (cases, invalid) = list_cases(&cli)?;
+    let order: HashMap<PathBuf, usize> = cases
+        .iter()
+        .map(|case| &case.fixture)
+        .chain(invalid.iter().map(|case| &case.fixture))
+        .enumerate()
+        .map(|(i, fixture)| (fixture.clone(), i))
+        .collect();
+    let (cases, unsynced): (Vec<Case>, Vec<Case>) = cases.into_iter().partition(|case| {
+        case.error.is_some() || head.is_none_or(|head| case.last_block().is_none_or(|n| n <= head))
+    });
+    let mut summary = if !cli.subscribe {
+        run_rpc(rpc_url, spec_version, cases, &cli, &profile).await
+    } else {
+        run_ws(ws_url, cases, &cli, &profile).await
+    };
+    let mode = if cli.subscribe { Mode::Ws } else { Mode::Rpc };
+    for case in invalid {
+        let res = Err(anyhow!("invalid case: {}", case.reason));
+        summary.record(FixtureResult::new(
+            case.fixture,
+            mode,
+            Duration::ZERO,
+            None,
+            res,
+        ));
+    }
+    for case in unsynced {
+        let reason = format!(
+            "range ends at block {}, node head is {}",
+            case.last_block().unwrap_or_default(),
+            head.unwrap_or_default()
+        );
+        summary.record(FixtureResult::skipped(case.fixture, mode, reason));
+    }
+    summary
+        .results
+        .sort_by_key(|result| order.get(&result.fixture).copied());
+    summary.print();
+    if let Some(path) = &cli.report_junit {
+        write_junit(&summary, path)?;
+    }
+    if let Some(path) = &cli.report_json {
+        write_json(&summary, path)?;
+    }
+    if summary.is_success() {
+        Ok(())
     } else {
-        let ws_url: Url = cli.pathfinder_ws_url.parse()?;
-        run_ws(ws_url, path_str).await
+        Err(anyhow!("some fixtures did not pass"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use serde_json::json;
+    use starknet::{
+        core::types::{Felt, ReorgData},
+        providers::jsonrpc::HttpTransport,
+    };
+
+    use std::fs;
+    use std::path::Path;
+
+    use super::{check_rpc_fixture, demux_batches, run_ws};
+    use starknet_event_query::{
+        block_ref::BlockRef,
+        config::Cli,
+        fixture_set::FixtureSet,
+        manifest::{Case, MANIFEST_FILE, load_cases},
+        mock_rpc,
+        mock_ws::{self, ScriptedReorg},
+        normalize::Profile,
+        outcome::{FixtureResult, Mode, Outcome, Summary},
+    };
+
+    fn event(block_number: u64, from_address: &str) -> String {
+        json!({
+            "block_number": block_number,
+            "from_address": from_address,
+            "keys": ["0x9"],
+            "data": ["0x1"],
+            "transaction_hash": format!("{:#x}", 0x100 + block_number)
+        })
+        .to_string()
+    }
+
+    // blocks 10 to 12, with a filtered case taking the events of 0x1
+    fn write_fixtures(fixture_dir: &Path, filtered: &[String]) {
+        let events = [
+            event(10, "0x1"),
+            event(10, "0x2"),
+            event(11, "0x1"),
+            event(12, "0x2"),
+            event(12, "0x1"),
+        ];
+        fs::write(fixture_dir.join("10+2.jsonl"), events.join("\n")).unwrap();
+        fs::write(fixture_dir.join("10+2f1.json"), r#"{"address":"0x1"}"#).unwrap();
+        fs::write(fixture_dir.join("10+2w1.jsonl"), filtered.join("\n")).unwrap();
+    }
+
+    async fn start_mock(fixture_dir: &Path) -> HttpTransport {
+        let url = mock_rpc::spawn(mock_rpc::Server {
+            fixtures: FixtureSet::load(fixture_dir).unwrap(),
+            max_chunk_size: 1024,
+            max_keys: 16,
+            spec_version: "0.8.1".to_string(),
+            chain_id: Felt::ONE,
+            head: 12,
+        })
+        .await
+        .unwrap();
+        HttpTransport::new(url)
+    }
+
+    async fn check_all(fixture_dir: &Path, args: &[&str]) -> Vec<eyre::Result<()>> {
+        let transport = start_mock(fixture_dir).await;
+        let dir = fixture_dir.to_str().unwrap();
+        let cli = Cli::parse_from(
+            ["starknet-event-query", "--fixture-dir", dir]
+                .iter()
+                .chain(args),
+        );
+        let profile = Profile::load("0.8").unwrap();
+        let mut results = Vec::new();
+        for case in load_cases(fixture_dir).unwrap() {
+            let mut stats = None;
+            results
+                .push(check_rpc_fixture(&transport, None, &case, &cli, &profile, &mut stats).await);
+        }
+        results
+    }
+
+    #[tokio::test]
+    async fn mock_passes_its_own_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        write_fixtures(
+            dir.path(),
+            &[event(10, "0x1"), event(11, "0x1"), event(12, "0x1")],
+        );
+        let results = check_all(dir.path(), &["--page-sizes", "1,2,5", "--check-tokens"]).await;
+        assert_eq!(results.len(), 2);
+        for res in results {
+            res.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn derived_results_catch_a_short_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        write_fixtures(dir.path(), &[event(10, "0x1"), event(12, "0x1")]);
+        let results = check_all(dir.path(), &["--derive"]).await;
+        assert!(results[0].is_ok());
+        let err = results[1].as_ref().unwrap_err();
+        assert!(format!("{:#}", err).contains("1 missing"), "{:#}", err);
+    }
+
+    // subscribe mode against the mock, settling at once
+    async fn run_ws_all(fixture_dir: &Path, reorgs: Vec<ScriptedReorg>, args: &[&str]) -> Summary {
+        let server = mock_ws::Server::new(FixtureSet::load(fixture_dir).unwrap(), reorgs);
+        let url = mock_ws::spawn(server).await.unwrap();
+        let dir = fixture_dir.to_str().unwrap();
+        let cli = Cli::parse_from(
+            [
+                "starknet-event-query",
+                "--subscribe",
+                "--settle-time",
+                "0",
+                "--fixture-timeout",
+                "10",
+                "--fixture-dir",
+                dir,
+            ]
+            .iter()
+            .chain(args),
+        );
+        let profile = Profile::load("0.8").unwrap();
+        run_ws(url, load_cases(fixture_dir).unwrap(), &cli, &profile).await
+    }
+
+    fn outcome_names(summary: &Summary) -> Vec<&str> {
+        summary
+            .results
+            .iter()
+            .map(|result| match result.outcome {
+                Outcome::Pass => "pass",
+                Outcome::Fail { .. } => "fail",
+                Outcome::Error(_) => "error",
+                Outcome::Skip(_) => "skip",
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn subscriptions_pass_their_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        write_fixtures(
+            dir.path(),
+            &[event(10, "0x1"), event(11, "0x1"), event(12, "0x1")],
+        );
+        for args in [&[][..], &["--connection-per-fixture"], &["--demux", "2"]] {
+            let summary = run_ws_all(dir.path(), Vec::new(), args).await;
+            assert_eq!(outcome_names(&summary), ["pass", "pass"], "{:?}", args);
+        }
+    }
+
+    #[tokio::test]
+    async fn subscription_missing_an_event_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        // the mock serves the short stored fixture, the derived one has
+        // all three events
+        write_fixtures(dir.path(), &[event(10, "0x1"), event(12, "0x1")]);
+        let summary = run_ws_all(dir.path(), Vec::new(), &["--derive"]).await;
+        assert_eq!(outcome_names(&summary), ["pass", "fail"]);
+        let Outcome::Fail { message, .. } = &summary.results[1].outcome else {
+            unreachable!();
+        };
+        assert!(message.contains("1 missing"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn empty_subscription_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        write_fixtures(
+            dir.path(),
+            &[event(10, "0x1"), event(11, "0x1"), event(12, "0x1")],
+        );
+        // no event of the range comes from 0x5
+        fs::write(dir.path().join("10+2f2.json"), r#"{"address":"0x5"}"#).unwrap();
+        fs::write(dir.path().join("10+2w2.jsonl"), "").unwrap();
+        let summary = run_ws_all(dir.path(), Vec::new(), &[]).await;
+        assert_eq!(outcome_names(&summary), ["pass", "pass", "pass"]);
+        let empty = &summary.results[2];
+        assert_eq!(empty.fixture, dir.path().join("10+2w2.jsonl"));
+        assert_eq!(empty.stats.unwrap().event_count, 0);
+    }
+
+    #[tokio::test]
+    async fn reorg_fails_the_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        write_fixtures(
+            dir.path(),
+            &[event(10, "0x1"), event(11, "0x1"), event(12, "0x1")],
+        );
+        let reorg: ReorgData = serde_json::from_value(json!({
+            "starting_block_hash": "0xb",
+            "starting_block_number": 11,
+            "ending_block_hash": "0xc",
+            "ending_block_number": 12
+        }))
+        .unwrap();
+        let reorgs = vec![ScriptedReorg {
+            after_block: 10,
+            reorg,
+        }];
+        let summary = run_ws_all(dir.path(), reorgs, &[]).await;
+        for result in summary.results.iter() {
+            let Outcome::Error(message) = &result.outcome else {
+                panic!("{:?} not an error", result.fixture);
+            };
+            assert!(
+                message.contains("encountered reorg 11 -> 12"),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn pending_events_must_match_the_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = json!({
+            "cases": [{
+                "name": "pending",
+                "from_block": "pending",
+                "to_block": "pending",
+                "address": "0x1"
+            }]
+        });
+        fs::write(dir.path().join(MANIFEST_FILE), manifest.to_string()).unwrap();
+        let pending = |from_address: &str| {
+            json!({
+                "from_address": from_address,
+                "keys": ["0x9"],
+                "data": [],
+                "transaction_hash": "0x3"
+            })
+            .to_string()
+        };
+        let fixture = dir.path().join("pending.jsonl");
+        fs::write(&fixture, pending("0x1")).unwrap();
+        check_all(dir.path(), &[]).await.remove(0).unwrap();
+
+        fs::write(&fixture, [pending("0x1"), pending("0x2")].join("\n")).unwrap();
+        let err = check_all(dir.path(), &[]).await.remove(0).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("event doesn't match the filter"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_head_range_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = json!({
+            "cases": [{ "name": "head", "from_block": "latest", "to_block": "latest" }]
+        });
+        fs::write(dir.path().join(MANIFEST_FILE), manifest.to_string()).unwrap();
+        fs::write(dir.path().join("head.jsonl"), "").unwrap();
+        let res = check_all(dir.path(), &[]).await.remove(0);
+        let result = FixtureResult::new(
+            dir.path().join("head.jsonl"),
+            Mode::Rpc,
+            Default::default(),
+            None,
+            res,
+        );
+        assert!(matches!(result.outcome, Outcome::Skip(_)));
+    }
+
+    fn case(name: &str, address: Option<u64>, keys: Option<Vec<Vec<u64>>>) -> Case {
+        Case {
+            name: name.to_string(),
+            fixture: format!("{}.jsonl", name).into(),
+            from_block: BlockRef::Number(10),
+            to_block: BlockRef::Number(12),
+            covered_to_block: None,
+            address: address.map(Felt::from),
+            keys: keys.map(|keys| {
+                keys.into_iter()
+                    .map(|alt| alt.into_iter().map(Felt::from).collect())
+                    .collect()
+            }),
+            error: None,
+            description: None,
+            base: None,
+            block_hashes: None,
+            page_sizes: None,
+        }
+    }
+
+    fn batch_names(cases: &[Case], width: usize) -> Vec<Vec<&str>> {
+        demux_batches(cases, width)
+            .into_iter()
+            .map(|batch| batch.into_iter().map(|case| case.name.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn demux_batches_are_capped() {
+        let cases = [
+            case("a", Some(1), None),
+            case("b", Some(2), None),
+            case("c", Some(3), None),
+        ];
+        assert_eq!(batch_names(&cases, 2), [vec!["a", "b"], vec!["c"]]);
+        assert_eq!(batch_names(&cases, 1), [["a"], ["b"], ["c"]]);
+    }
+
+    #[test]
+    fn unfiltered_cases_go_alone() {
+        let mut open = case("open", Some(2), None);
+        open.to_block = BlockRef::Latest;
+        open.covered_to_block = Some(12);
+        let cases = [
+            case("all", None, None),
+            case("a", Some(1), None),
+            open,
+            case("b", Some(3), None),
+        ];
+        assert_eq!(
+            batch_names(&cases, 4),
+            [vec!["all"], vec!["open"], vec!["a", "b"]]
+        );
+    }
+
+    // an address filter overlaps a keys filter, keys overlap when a position
+    // shares an alternative
+    #[test]
+    fn overlapping_filters_are_split() {
+        let cases = [
+            case("a", Some(1), None),
+            case("a-key", Some(1), Some(vec![vec![5]])),
+            case("key", None, Some(vec![vec![5, 6]])),
+            case("other-key", None, Some(vec![vec![7]])),
+        ];
+        assert_eq!(
+            batch_names(&cases, 4),
+            [vec!["a"], vec!["a-key", "other-key"], vec!["key"]]
+        );
     }
 }
@@ -0,0 +1,595 @@
+use eyre::anyhow;
+use serde_json::json;
+use starknet::core::types::Felt;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::block_ref::BlockRef;
+use crate::derive::list_fixtures;
+use crate::error_case::ErrorCase;
+use crate::filter_seed::{FilterSeed, parse_filter_map};
+use crate::matcher::EventMatcher;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+// one fixture case, from the directory manifest or decoded from a legacy
+// file name; fixture is where its expected events are (or would be) stored
+pub struct Case {
+    pub name: String,
+    pub fixture: PathBuf,
+    pub from_block: BlockRef,
+    pub to_block: BlockRef,
+    // last block the expected events cover when the range ends at a moving
+    // tag, required then
+    pub covered_to_block: Option<u64>,
+    pub address: Option<Felt>,
+    pub keys: Option<Vec<Vec<Felt>>>,
+    pub error: Option<ErrorCase>,
+    pub description: Option<String>,
+    // stored unfiltered fixture of the same range
+    pub base: Option<PathBuf>,
+    pub block_hashes: Option<PathBuf>,
+    // page sizes to query the case with instead of the command line ones
+    pub page_sizes: Option<Vec<u64>>,
+}
+
+impl Case {
+    pub fn is_filtered(&self) -> bool {
+        self.address.is_some() || self.keys.is_some()
+    }
+
+    pub fn block_range(&self) -> Option<(u64, u64)> {
+        Some((self.from_block.number()?, self.to_block.number()?))
+    }
+
+    // None when the range ends at a block hash or an open moving tag
+    pub fn last_block(&self) -> Option<u64> {
+        match self.to_block {
+            BlockRef::Number(n) => Some(n),
+            _ if self.to_block.is_moving() => self.covered_to_block,
+            _ => None,
+        }
+    }
+
+    // None unless both ends are block numbers
+    pub fn matcher(&self) -> Option<EventMatcher> {
+        let (from_block, to_block) = self.block_range()?;
+        Some(EventMatcher::new(
+            from_block,
+            to_block,
+            self.address,
+            self.keys.clone(),
+        ))
+    }
+
+    // a filter file may override the range encoded in the fixture name,
+    // e.g. to end it at a tag
+    fn from_legacy(fixture: PathBuf) -> eyre::Result<Self> {
+        let filter_seed = FilterSeed::load(&fixture)?;
+        let filter_map = filter_seed.load_filter_map(&fixture)?.unwrap_or_default();
+        let (address, keys) = parse_filter_map(&filter_map)?;
+        let from_block = match filter_map.get("from_block") {
+            Some(v) => BlockRef::from_json(v)?,
+            None => BlockRef::Number(filter_seed.from_block),
+        };
+        let to_block = match filter_map.get("to_block") {
+            Some(v) => BlockRef::from_json(v)?,
+            None => BlockRef::Number(filter_seed.to_block),
+        };
+        let name = fixture
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("invalid fixture name: {:?}", fixture))?
+            .to_string();
+        Self {
+            name,
+            error: ErrorCase::from_map(&filter_map)?,
+            fixture,
+            from_block,
+            to_block,
+            covered_to_block: parse_covered_to_block(&filter_map)?,
+            address,
+            keys,
+            description: None,
+            base: None,
+            block_hashes: None,
+            page_sizes: None,
+        }
+        .validate()
+    }
+
+    fn from_manifest(fixture_dir: &Path, value: &serde_json::Value) -> eyre::Result<Self> {
+        let case_map: HashMap<String, serde_json::Value> = serde_json::from_value(value.clone())?;
+        let name = case_map
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("manifest case w/o name"))?
+            .to_string();
+        let from_block = BlockRef::from_json(
+            case_map
+                .get("from_block")
+                .ok_or_else(|| anyhow!("manifest case {} w/o from block", name))?,
+        )?;
+        let to_block = match case_map.get("to_block") {
+            Some(v) => BlockRef::from_json(v)?,
+            None => from_block.clone(),
+        };
+        let expected = match case_map.get("expected") {
+            Some(v) => v
+                .as_str()
+                .ok_or_else(|| anyhow!("manifest case {} has invalid expected file", name))?
+                .to_string(),
+            None => format!("{}.jsonl", name),
+        };
+        let (address, keys) = parse_filter_map(&case_map)?;
+        Self {
+            fixture: fixture_dir.join(expected),
+            from_block,
+            to_block,
+            covered_to_block: parse_covered_to_block(&case_map)?,
+            address,
+            keys,
+            error: ErrorCase::from_map(&case_map)?,
+            description: case_map
+                .get("description")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            base: None,
+            block_hashes: match case_map.get("block_hashes") {
+                Some(v) => Some(fixture_dir.join(v.as_str().ok_or_else(|| {
+                    anyhow!("manifest case {} has invalid block hashes file", name)
+                })?)),
+                None => None,
+            },
+            page_sizes: parse_page_sizes(&case_map)?,
+            name,
+        }
+        .validate()
+    }
+
+    // a range ending at a moving tag leaves nothing to compare with unless
+    // the case says how far its expected events reach; one starting at a
+    // moving tag is only matched against the filter
+    fn validate(self) -> eyre::Result<Self> {
+        if self.to_block.is_moving()
+            && !self.from_block.is_moving()
+            && self.covered_to_block.is_none()
+            && self.error.is_none()
+        {
+            return Err(anyhow!(
+                "case {} ends at {} w/o covered_to_block",
+                self.name,
+                self.to_block
+            ));
+        }
+
+        Ok(self)
+    }
+
+    pub fn to_manifest(&self) -> serde_json::Value {
+        let mut case_map = serde_json::Map::new();
+        case_map.insert("name".to_string(), json!(self.name));
+        case_map.insert("from_block".to_string(), self.from_block.to_json());
+        case_map.insert("to_block".to_string(), self.to_block.to_json());
+        if let Some(covered_to_block) = self.covered_to_block {
+            case_map.insert("covered_to_block".to_string(), json!(covered_to_block));
+        }
+        if let Some(address) = &self.address {
+            case_map.insert("address".to_string(), json!(format!("{:#x}", address)));
+        }
+        if let Some(keys) = &self.keys {
+            let keys: Vec<Vec<String>> = keys
+                .iter()
+                .map(|alt| alt.iter().map(|k| format!("{:#x}", k)).collect())
+                .collect();
+            case_map.insert("keys".to_string(), json!(keys));
+        }
+        if self.fixture.exists()
+            && let Some(expected) = self.fixture.file_name().and_then(|s| s.to_str())
+        {
+            case_map.insert("expected".to_string(), json!(expected));
+        }
+        if let Some(block_hashes) = self
+            .block_hashes
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+        {
+            case_map.insert("block_hashes".to_string(), json!(block_hashes));
+        }
+        if let Some(page_sizes) = &self.page_sizes {
+            case_map.insert("page_sizes".to_string(), json!(page_sizes));
+        }
+        if let Some(error) = &self.error {
+            if let Some(chunk_size) = error.chunk_size {
+                case_map.insert("chunk_size".to_string(), json!(chunk_size));
+            }
+            if let Some(token) = &error.continuation_token {
+                case_map.insert("continuation_token".to_string(), json!(token));
+            }
+            let mut expected_error = json!({ "code": error.code });
+            if let Some(message) = &error.message {
+                expected_error["message"] = json!(message);
+            }
+            case_map.insert("expected_error".to_string(), expected_error);
+        }
+        if let Some(description) = &self.description {
+            case_map.insert("description".to_string(), json!(description));
+        }
+
+        serde_json::Value::Object(case_map)
+    }
+}
+
+// a case that couldn't be read; it fails on its own instead of taking the
+// directory's other cases with it
+pub struct InvalidCase {
+    pub name: String,
+    pub fixture: PathBuf,
+    pub reason: String,
+}
+
+// cases listed in the manifest, followed by legacy fixtures the manifest
+// doesn't mention
+pub fn load_cases(fixture_dir: &Path) -> eyre::Result<Vec<Case>> {
+    let (cases, invalid) = read_cases(fixture_dir)?;
+    match invalid.first() {
+        Some(invalid) => Err(anyhow!("case {}: {}", invalid.name, invalid.reason)),
+        None => Ok(cases),
+    }
+}
+
+// like load_cases, but keeps the cases that can't be read apart
+pub fn read_cases(fixture_dir: &Path) -> eyre::Result<(Vec<Case>, Vec<InvalidCase>)> {
+    let mut cases = Vec::new();
+    let mut invalid = Vec::new();
+    for case in read_manifest(fixture_dir)? {
+        match case {
+            Ok(case) => cases.push(case),
+            Err(case) => invalid.push(case),
+        }
+    }
+    let claimed: HashSet<PathBuf> = cases
+        .iter()
+        .map(|c| c.fixture.clone())
+        .chain(invalid.iter().map(|c| c.fixture.clone()))
+        .collect();
+    for fixture in list_fixtures(fixture_dir)? {
+        if claimed.contains(&fixture) {
+            continue;
+        }
+
+        match Case::from_legacy(fixture.clone()) {
+            Ok(case) => cases.push(case),
+            Err(err) => invalid.push(InvalidCase {
+                name: fixture
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                fixture,
+                reason: format!("{:#}", err),
+            }),
+        }
+    }
+
+    let mut bases = HashMap::new();
+    for case in cases.iter() {
+        if !case.is_filtered()
+            && case.error.is_none()
+            && case.fixture.exists()
+            && let Some(range) = case.block_range()
+        {
+            bases.entry(range).or_insert_with(|| case.fixture.clone());
+        }
+    }
+    for case in cases.iter_mut() {
+        let Some((from_block, to_block)) = case.block_range() else {
+            continue;
+        };
+
+        if case.is_filtered() {
+            case.base = bases.get(&(from_block, to_block)).cloned();
+        }
+        if case.block_hashes.is_none() {
+            let seed = FilterSeed {
+                from_block,
+                to_block,
+                with_name: None,
+            };
+            let path = fixture_dir.join(seed.format_block_hashes_basename());
+            if path.exists() {
+                case.block_hashes = Some(path);
+            }
+        }
+    }
+
+    Ok((cases, invalid))
+}
+
+pub fn load_manifest(fixture_dir: &Path) -> eyre::Result<Vec<Case>> {
+    read_manifest(fixture_dir)?
+        .into_iter()
+        .map(|case| case.map_err(|case| anyhow!("case {}: {}", case.name, case.reason)))
+        .collect()
+}
+
+// a case without a name is reported as the manifest's own
+fn read_manifest(fixture_dir: &Path) -> eyre::Result<Vec<Result<Case, InvalidCase>>> {
+    let manifest_path = fixture_dir.join(MANIFEST_FILE);
+    if !manifest_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest_path)?)?;
+    let raw_cases = manifest["cases"]
+        .as_array()
+        .ok_or_else(|| anyhow!("manifest {:?} w/o cases", manifest_path))?;
+    let mut cases = Vec::new();
+    for (i, raw_case) in raw_cases.iter().enumerate() {
+        cases.push(Case::from_manifest(fixture_dir, raw_case).map_err(|err| {
+            let (name, fixture) = match raw_case["name"].as_str() {
+                Some(name) => {
+                    let expected = match raw_case["expected"].as_str() {
+                        Some(expected) => expected.to_string(),
+                        None => format!("{}.jsonl", name),
+                    };
+                    (name.to_string(), fixture_dir.join(expected))
+                }
+                None => (format!("#{}", i + 1), manifest_path.clone()),
+            };
+            InvalidCase {
+                name,
+                fixture,
+                reason: format!("{:#}", err),
+            }
+        }));
+    }
+
+    Ok(cases)
+}
+
+pub fn write_manifest(fixture_dir: &Path, cases: &[Case]) -> eyre::Result<()> {
+    let raw_cases: Vec<serde_json::Value> = cases.iter().map(|c| c.to_manifest()).collect();
+    let manifest = json!({ "cases": raw_cases });
+    fs::write(
+        fixture_dir.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(())
+}
+
+fn parse_covered_to_block(
+    case_map: &HashMap<String, serde_json::Value>,
+) -> eyre::Result<Option<u64>> {
+    match case_map.get("covered_to_block") {
+        Some(v) => {
+            Ok(Some(v.as_u64().ok_or_else(|| {
+                anyhow!("invalid covered to block: {}", v)
+            })?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn parse_page_sizes(
+    case_map: &HashMap<String, serde_json::Value>,
+) -> eyre::Result<Option<Vec<u64>>> {
+    let Some(v) = case_map.get("page_sizes") else {
+        return Ok(None);
+    };
+
+    let page_sizes = v
+        .as_array()
+        .ok_or_else(|| anyhow!("page sizes not an array: {}", v))?
+        .iter()
+        .map(|size| match size.as_u64() {
+            Some(n) if n > 0 => Ok(n),
+            _ => Err(anyhow!("invalid page size: {}", size)),
+        })
+        .collect::<eyre::Result<Vec<u64>>>()?;
+    if page_sizes.is_empty() {
+        return Err(anyhow!("empty page sizes"));
+    }
+
+    Ok(Some(page_sizes))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use std::fs;
+
+    use super::{MANIFEST_FILE, load_cases, load_manifest, read_cases, write_manifest};
+    use crate::block_ref::BlockRef;
+
+    fn write_raw_manifest(fixture_dir: &std::path::Path, cases: serde_json::Value) {
+        fs::write(
+            fixture_dir.join(MANIFEST_FILE),
+            json!({ "cases": cases }).to_string(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn loads_manifest_cases() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([{
+                "name": "transfers",
+                "from_block": 10,
+                "to_block": "latest",
+                "covered_to_block": 20,
+                "address": "0x49d",
+                "keys": [["0x1", "0x2"], []],
+                "page_sizes": [1, 7]
+            }]),
+        );
+        let cases = load_manifest(dir.path()).unwrap();
+        assert_eq!(cases.len(), 1);
+        let case = &cases[0];
+        assert_eq!(case.name, "transfers");
+        assert_eq!(case.fixture, dir.path().join("transfers.jsonl"));
+        assert_eq!(case.from_block, BlockRef::Number(10));
+        assert_eq!(case.to_block, BlockRef::Latest);
+        assert_eq!(case.last_block(), Some(20));
+        assert_eq!(case.address, Some(Felt::from(0x49d_u64)));
+        assert_eq!(
+            case.keys,
+            Some(vec![vec![Felt::ONE, Felt::TWO], Vec::new()])
+        );
+        assert_eq!(case.page_sizes, Some(vec![1, 7]));
+        assert!(case.matcher().is_none());
+    }
+
+    #[test]
+    fn to_block_defaults_to_from_block() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(dir.path(), json!([{ "name": "one", "from_block": 5 }]));
+        let cases = load_manifest(dir.path()).unwrap();
+        assert_eq!(cases[0].block_range(), Some((5, 5)));
+        assert_eq!(cases[0].page_sizes, None);
+    }
+
+    #[test]
+    fn rejects_invalid_cases() {
+        for case in [
+            json!({ "from_block": 5 }),
+            json!({ "name": "no range" }),
+            json!({ "name": "zero", "from_block": 5, "page_sizes": [0] }),
+            json!({ "name": "empty", "from_block": 5, "page_sizes": [] }),
+            json!({ "name": "open", "from_block": 5, "to_block": "latest" }),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            write_raw_manifest(dir.path(), json!([case]));
+            assert!(load_manifest(dir.path()).is_err(), "{}", case);
+        }
+    }
+
+    #[test]
+    fn accepts_l1_accepted() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([{
+                "name": "l1",
+                "from_block": 5,
+                "to_block": "l1_accepted",
+                "covered_to_block": 9
+            }]),
+        );
+        let cases = load_manifest(dir.path()).unwrap();
+        assert_eq!(cases[0].to_block, BlockRef::L1Accepted);
+        assert_eq!(cases[0].last_block(), Some(9));
+    }
+
+    #[test]
+    fn moving_ranges_need_no_covered_to_block() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([{ "name": "head", "from_block": "latest", "to_block": "latest" }]),
+        );
+        let cases = load_manifest(dir.path()).unwrap();
+        assert_eq!(cases[0].last_block(), None);
+    }
+
+    #[test]
+    fn missing_manifest_has_no_cases() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_manifest(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn written_manifest_loads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([{
+                "name": "filtered",
+                "from_block": 1,
+                "to_block": 2,
+                "keys": [["0x3"]],
+                "page_sizes": [2],
+                "description": "one key"
+            }]),
+        );
+        let cases = load_manifest(dir.path()).unwrap();
+        write_manifest(dir.path(), &cases).unwrap();
+        let reloaded = load_manifest(dir.path()).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].to_manifest(), cases[0].to_manifest());
+        assert_eq!(reloaded[0].page_sizes, Some(vec![2]));
+    }
+
+    #[test]
+    fn legacy_fixtures_follow_manifest_cases() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([{ "name": "listed", "from_block": 1, "to_block": 2 }]),
+        );
+        fs::write(dir.path().join("listed.jsonl"), "").unwrap();
+        fs::write(dir.path().join("3+4.jsonl"), "").unwrap();
+        let cases = load_cases(dir.path()).unwrap();
+        let names: Vec<&str> = cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["listed", "3+4"]);
+        assert_eq!(cases[1].block_range(), Some((3, 7)));
+    }
+
+    #[test]
+    fn only_legacy_filter_names_are_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([{
+                "name": "transfers",
+                "from_block": 1,
+                "to_block": 2,
+                "block_hashes": "transfers-hashes.json"
+            }]),
+        );
+        fs::write(dir.path().join("transfers-hashes.json"), "{}").unwrap();
+        fs::write(dir.path().join("profile.json"), "{}").unwrap();
+        fs::write(dir.path().join("3+4.jsonl"), "").unwrap();
+        fs::write(dir.path().join("3+4f1.json"), r#"{"address":"0x1"}"#).unwrap();
+        let cases = load_cases(dir.path()).unwrap();
+        let names: Vec<&str> = cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["transfers", "3+4", "3+4w1"]);
+    }
+
+    #[test]
+    fn invalid_cases_are_kept_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        write_raw_manifest(
+            dir.path(),
+            json!([
+                { "name": "good", "from_block": 1, "to_block": 2 },
+                { "name": "open", "from_block": 1, "to_block": "latest" },
+                { "from_block": 1 }
+            ]),
+        );
+        fs::write(dir.path().join("3+4.jsonl"), "").unwrap();
+        fs::write(dir.path().join("3+4f1.json"), r#"{"to_block":"earliest"}"#).unwrap();
+        let (cases, invalid) = read_cases(dir.path()).unwrap();
+        let names: Vec<&str> = cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["good", "3+4"]);
+        let invalid: Vec<(&str, &std::path::Path)> = invalid
+            .iter()
+            .map(|c| (c.name.as_str(), c.fixture.as_path()))
+            .collect();
+        assert_eq!(
+            invalid,
+            [
+                ("open", dir.path().join("open.jsonl").as_path()),
+                ("#3", dir.path().join(MANIFEST_FILE).as_path()),
+                ("3+4w1", dir.path().join("3+4w1.jsonl").as_path()),
+            ]
+        );
+        assert!(load_cases(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use eyre::anyhow;
+use starknet::core::types::Felt;
+
+// block range is inclusive; a non-empty key position requires the event to
+// have one of its alternatives there, an empty position accepts anything
+pub struct EventMatcher {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub address: Option<Felt>,
+    pub keys: Option<Vec<Vec<Felt>>>,
+}
+
+impl EventMatcher {
+    pub fn new(
+        from_block: u64,
+        to_block: u64,
+        address: Option<Felt>,
+        keys: Option<Vec<Vec<Felt>>>,
+    ) -> Self {
+        Self {
+            from_block,
+            to_block,
+            address,
+            keys,
+        }
+    }
+
+    pub fn is_match(&self, event: &serde_json::Value) -> eyre::Result<bool> {
+        let block_number = event["block_number"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("event w/o block number"))?;
+        if block_number < self.from_block || block_number > self.to_block {
+            return Ok(false);
+        }
+
+        self.is_filter_match(event)
+    }
+
+    // address and keys only, for events of the pending block, which have no
+    // block number to check the range with
+    pub fn is_filter_match(&self, event: &serde_json::Value) -> eyre::Result<bool> {
+        if let Some(address) = &self.address {
+            let serde_json::Value::String(ref from_address) = event["from_address"] else {
+                return Err(anyhow!("unexpected address type"));
+            };
+            if Felt::from_hex(from_address)? != *address {
+                return Ok(false);
+            }
+        }
+
+        let Some(keys) = &self.keys else {
+            return Ok(true);
+        };
+
+        let serde_json::Value::Array(ref event_keys) = event["keys"] else {
+            return Err(anyhow!("unexpected keys type"));
+        };
+        for (pos, alt) in keys.iter().enumerate() {
+            if alt.is_empty() {
+                continue;
+            }
+
+            let Some(key) = event_keys.get(pos) else {
+                return Ok(false);
+            };
+            let serde_json::Value::String(k) = key else {
+                return Err(anyhow!("unexpected key type"));
+            };
+            if !alt.contains(&Felt::from_hex(k)?) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    // no event can match both filters; block ranges don't count, as a
+    // subscription also gets the events past its range
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        if let (Some(a), Some(b)) = (self.address, other.address)
+            && a != b
+        {
+            return true;
+        }
+
+        let (Some(keys), Some(other_keys)) = (&self.keys, &other.keys) else {
+            return false;
+        };
+        keys.iter().zip(other_keys.iter()).any(|(alt, other_alt)| {
+            !alt.is_empty()
+                && !other_alt.is_empty()
+                && !alt.iter().any(|key| other_alt.contains(key))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use super::EventMatcher;
+
+    fn event(block_number: u64, from_address: &str, keys: &[&str]) -> serde_json::Value {
+        json!({
+            "block_number": block_number,
+            "from_address": from_address,
+            "keys": keys,
+            "data": [],
+            "transaction_hash": "0x1"
+        })
+    }
+
+    #[test]
+    fn block_range_is_inclusive() {
+        let matcher = EventMatcher::new(10, 20, None, None);
+        assert!(!matcher.is_match(&event(9, "0x1", &[])).unwrap());
+        assert!(matcher.is_match(&event(10, "0x1", &[])).unwrap());
+        assert!(matcher.is_match(&event(20, "0x1", &[])).unwrap());
+        assert!(!matcher.is_match(&event(21, "0x1", &[])).unwrap());
+    }
+
+    #[test]
+    fn address_compares_felts() {
+        let matcher = EventMatcher::new(0, 100, Some(Felt::from(0xabcu64)), None);
+        assert!(matcher.is_match(&event(1, "0xabc", &[])).unwrap());
+        assert!(matcher.is_match(&event(1, "0x0ABC", &[])).unwrap());
+        assert!(!matcher.is_match(&event(1, "0xabd", &[])).unwrap());
+    }
+
+    #[test]
+    fn empty_key_position_is_a_wildcard() {
+        let keys = vec![vec![], vec![Felt::from(2u64)]];
+        let matcher = EventMatcher::new(0, 100, None, Some(keys));
+        assert!(matcher.is_match(&event(1, "0x1", &["0x7", "0x2"])).unwrap());
+        assert!(
+            matcher
+                .is_match(&event(1, "0x1", &["0x8", "0x2", "0x9"]))
+                .unwrap()
+        );
+        assert!(!matcher.is_match(&event(1, "0x1", &["0x7", "0x3"])).unwrap());
+    }
+
+    #[test]
+    fn key_positions_accept_any_alternative() {
+        let keys = vec![vec![Felt::from(1u64), Felt::from(2u64)]];
+        let matcher = EventMatcher::new(0, 100, None, Some(keys));
+        assert!(matcher.is_match(&event(1, "0x1", &["0x1"])).unwrap());
+        assert!(matcher.is_match(&event(1, "0x1", &["0x2"])).unwrap());
+        assert!(!matcher.is_match(&event(1, "0x1", &["0x3"])).unwrap());
+    }
+
+    #[test]
+    fn keys_are_positional() {
+        let keys = vec![vec![Felt::from(1u64)], vec![Felt::from(2u64)]];
+        let matcher = EventMatcher::new(0, 100, None, Some(keys));
+        assert!(matcher.is_match(&event(1, "0x1", &["0x1", "0x2"])).unwrap());
+        assert!(!matcher.is_match(&event(1, "0x1", &["0x2", "0x1"])).unwrap());
+        // too short to have the second position
+        assert!(!matcher.is_match(&event(1, "0x1", &["0x1"])).unwrap());
+    }
+
+    #[test]
+    fn filter_match_ignores_block_number() {
+        let matcher = EventMatcher::new(10, 20, Some(Felt::ONE), Some(vec![vec![Felt::TWO]]));
+        let pending = json!({ "from_address": "0x1", "keys": ["0x2"] });
+        assert!(matcher.is_filter_match(&pending).unwrap());
+        assert!(
+            matcher
+                .is_filter_match(&event(30, "0x1", &["0x2"]))
+                .unwrap()
+        );
+        assert!(!matcher.is_match(&event(30, "0x1", &["0x2"])).unwrap());
+        assert!(
+            !matcher
+                .is_filter_match(&event(15, "0x2", &["0x2"]))
+                .unwrap()
+        );
+        assert!(
+            !matcher
+                .is_filter_match(&event(15, "0x1", &["0x1"]))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_events_are_errors() {
+        let matcher = EventMatcher::new(0, 100, Some(Felt::ONE), None);
+        assert!(matcher.is_match(&json!({ "from_address": "0x1" })).is_err());
+        assert!(
+            matcher
+                .is_match(&json!({ "block_number": 1, "from_address": 1 }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn disjoint_by_address_or_key_position() {
+        let a = EventMatcher::new(0, 10, Some(Felt::ONE), None);
+        let b = EventMatcher::new(0, 10, Some(Felt::TWO), None);
+        assert!(a.is_disjoint(&b));
+        assert!(!a.is_disjoint(&a));
+
+        let k1 = EventMatcher::new(0, 10, None, Some(vec![vec![], vec![Felt::ONE]]));
+        let k2 = EventMatcher::new(0, 10, None, Some(vec![vec![Felt::ONE], vec![Felt::TWO]]));
+        let k12 = EventMatcher::new(0, 10, None, Some(vec![vec![], vec![Felt::ONE, Felt::TWO]]));
+        assert!(k1.is_disjoint(&k2));
+        assert!(!k1.is_disjoint(&k12));
+        assert!(!k2.is_disjoint(&k12));
+        assert!(!k1.is_disjoint(&a));
+    }
+
+    #[test]
+    fn unfiltered_overlaps_everything() {
+        let all = EventMatcher::new(0, 10, None, None);
+        let later = EventMatcher::new(20, 30, None, None);
+        let filtered = EventMatcher::new(0, 10, Some(Felt::ONE), Some(vec![vec![Felt::ONE]]));
+        assert!(!all.is_disjoint(&filtered));
+        assert!(!filtered.is_disjoint(&all));
+        assert!(!all.is_disjoint(&later));
+    }
+}
@@ -0,0 +1,286 @@
+use eyre::anyhow;
+use serde_json::json;
+use starknet::core::types::Felt;
+use starknet::core::utils::{cairo_short_string_to_felt, parse_cairo_short_string};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const METADATA_FILE: &str = "metadata.json";
+
+// where the fixtures of a directory were captured, e.g.
+// {"chain_id": "SN_MAIN", "spec_version": "0.8.1",
+//  "node_version": "v0.16.0", "captured_at": "2025-04-28T09:12:44Z",
+//  "from_block": 0, "to_block": 1101000}
+// the block range spans every recorded fixture
+#[derive(Default)]
+pub struct Metadata {
+    pub chain_id: Option<Felt>,
+    pub spec_version: Option<String>,
+    pub node_version: Option<String>,
+    pub captured_at: Option<String>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+}
+
+impl Metadata {
+    pub fn load(fixture_dir: &Path) -> eyre::Result<Option<Self>> {
+        let path = fixture_dir.join(METADATA_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let metadata_map: HashMap<String, serde_json::Value> =
+            serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let string = |field: &str| -> eyre::Result<Option<String>> {
+            match metadata_map.get(field) {
+                Some(v) => Ok(Some(
+                    v.as_str()
+                        .ok_or_else(|| anyhow!("{} in {:?} not a string", field, path))?
+                        .to_string(),
+                )),
+                None => Ok(None),
+            }
+        };
+        let number = |field: &str| -> eyre::Result<Option<u64>> {
+            match metadata_map.get(field) {
+                Some(v) => Ok(Some(v.as_u64().ok_or_else(|| {
+                    anyhow!("{} in {:?} not a block number", field, path)
+                })?)),
+                None => Ok(None),
+            }
+        };
+        let chain_id = match string("chain_id")? {
+            Some(s) => Some(parse_chain_id(&s)?),
+            None => None,
+        };
+        Ok(Some(Self {
+            chain_id,
+            spec_version: string("spec_version")?,
+            node_version: string("node_version")?,
+            captured_at: string("captured_at")?,
+            from_block: number("from_block")?,
+            to_block: number("to_block")?,
+        }))
+    }
+
+    pub fn save(&self, fixture_dir: &Path) -> eyre::Result<()> {
+        let mut metadata_map = serde_json::Map::new();
+        if let Some(chain_id) = self.chain_id {
+            metadata_map.insert("chain_id".to_string(), json!(format_chain_id(chain_id)));
+        }
+        for (field, value) in [
+            ("spec_version", &self.spec_version),
+            ("node_version", &self.node_version),
+            ("captured_at", &self.captured_at),
+        ] {
+            if let Some(value) = value {
+                metadata_map.insert(field.to_string(), json!(value));
+            }
+        }
+        for (field, value) in [("from_block", self.from_block), ("to_block", self.to_block)] {
+            if let Some(value) = value {
+                metadata_map.insert(field.to_string(), json!(value));
+            }
+        }
+
+        fs::write(
+            fixture_dir.join(METADATA_FILE),
+            serde_json::to_string_pretty(&serde_json::Value::Object(metadata_map))?,
+        )?;
+        Ok(())
+    }
+
+    // takes the fields self doesn't have yet from node; the block range
+    // is extended separately
+    pub fn fill_missing(&mut self, node: Metadata) {
+        self.chain_id = self.chain_id.or(node.chain_id);
+        self.spec_version = self.spec_version.take().or(node.spec_version);
+        self.node_version = self.node_version.take().or(node.node_version);
+        self.captured_at = self.captured_at.take().or(node.captured_at);
+    }
+
+    pub fn extend_range(&mut self, from_block: u64, to_block: u64) {
+        self.from_block = Some(self.from_block.map_or(from_block, |n| n.min(from_block)));
+        self.to_block = Some(self.to_block.map_or(to_block, |n| n.max(to_block)));
+    }
+
+    // refuses to compare fixtures of one network with another
+    pub fn check_chain_id(&self, actual: Felt) -> eyre::Result<()> {
+        if let Some(expected) = self.chain_id
+            && expected != actual
+        {
+            return Err(anyhow!(
+                "fixtures were captured on {}, server is on {}",
+                format_chain_id(expected),
+                format_chain_id(actual)
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn now() -> eyre::Result<String> {
+    Ok(OffsetDateTime::now_utc()
+        .replace_nanosecond(0)?
+        .format(&Rfc3339)?)
+}
+
+// a short string like SN_MAIN or the hex felt
+fn parse_chain_id(s: &str) -> eyre::Result<Felt> {
+    if s.starts_with("0x") {
+        Ok(Felt::from_hex(s)?)
+    } else {
+        Ok(cairo_short_string_to_felt(s)?)
+    }
+}
+
+fn format_chain_id(chain_id: Felt) -> String {
+    match parse_cairo_short_string(&chain_id) {
+        Ok(s) if !s.is_empty() && s.bytes().all(|b| b.is_ascii_graphic()) => s,
+        _ => format!("{:#x}", chain_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{METADATA_FILE, Metadata, format_chain_id, parse_chain_id};
+    use starknet::core::types::Felt;
+
+    use std::fs;
+
+    #[test]
+    fn chain_ids_are_short_strings_or_hex() {
+        let mainnet = parse_chain_id("SN_MAIN").unwrap();
+        assert_eq!(mainnet, Felt::from_hex("0x534e5f4d41494e").unwrap());
+        assert_eq!(parse_chain_id("0x534e5f4d41494e").unwrap(), mainnet);
+        assert_eq!(format_chain_id(mainnet), "SN_MAIN");
+        // not a printable short string
+        assert_eq!(format_chain_id(Felt::from(1u64)), "0x1");
+        assert_eq!(format_chain_id(Felt::ZERO), "0x0");
+        assert!(parse_chain_id("0xnothex").is_err());
+    }
+
+    #[test]
+    fn refuses_another_chain() {
+        let metadata = Metadata {
+            chain_id: Some(parse_chain_id("SN_MAIN").unwrap()),
+            ..Default::default()
+        };
+        metadata
+            .check_chain_id(parse_chain_id("SN_MAIN").unwrap())
+            .unwrap();
+        let err = metadata
+            .check_chain_id(parse_chain_id("SN_SEPOLIA").unwrap())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "fixtures were captured on SN_MAIN, server is on SN_SEPOLIA"
+        );
+
+        // without a recorded chain, any server goes
+        Metadata::default()
+            .check_chain_id(parse_chain_id("SN_SEPOLIA").unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn saved_metadata_loads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Metadata::load(dir.path()).unwrap().is_none());
+
+        let metadata = Metadata {
+            chain_id: Some(parse_chain_id("SN_SEPOLIA").unwrap()),
+            spec_version: Some("0.8.1".to_string()),
+            node_version: Some("v0.16.0".to_string()),
+            captured_at: Some("2025-04-28T09:12:44Z".to_string()),
+            from_block: Some(10),
+            to_block: Some(12),
+        };
+        metadata.save(dir.path()).unwrap();
+        let contents = fs::read_to_string(dir.path().join(METADATA_FILE)).unwrap();
+        assert!(contents.contains(r#""chain_id": "SN_SEPOLIA""#));
+
+        let loaded = Metadata::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.chain_id, metadata.chain_id);
+        assert_eq!(loaded.spec_version, metadata.spec_version);
+        assert_eq!(loaded.node_version, metadata.node_version);
+        assert_eq!(loaded.captured_at, metadata.captured_at);
+        assert_eq!(loaded.from_block, Some(10));
+        assert_eq!(loaded.to_block, Some(12));
+
+        // absent fields stay absent
+        Metadata::default().save(dir.path()).unwrap();
+        let loaded = Metadata::load(dir.path()).unwrap().unwrap();
+        assert!(loaded.chain_id.is_none());
+        assert!(loaded.spec_version.is_none());
+        assert!(loaded.to_block.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for contents in [
+            r#"{"chain_id": 1}"#,
+            r#"{"to_block": "12"}"#,
+            r#"{"from_block": -1}"#,
+            "not json",
+        ] {
+            fs::write(dir.path().join(METADATA_FILE), contents).unwrap();
+            assert!(Metadata::load(dir.path()).is_err(), "{}", contents);
+        }
+    }
+
+    #[test]
+    fn range_spans_every_fixture() {
+        let mut metadata = Metadata::default();
+        metadata.extend_range(10, 12);
+        assert_eq!(
+            (metadata.from_block, metadata.to_block),
+            (Some(10), Some(12))
+        );
+        metadata.extend_range(5, 11);
+        assert_eq!(
+            (metadata.from_block, metadata.to_block),
+            (Some(5), Some(12))
+        );
+        metadata.extend_range(11, 20);
+        assert_eq!(
+            (metadata.from_block, metadata.to_block),
+            (Some(5), Some(20))
+        );
+    }
+
+    #[test]
+    fn fills_only_missing_fields() {
+        let mut metadata = Metadata {
+            chain_id: Some(parse_chain_id("SN_MAIN").unwrap()),
+            spec_version: Some("0.8.1".to_string()),
+            from_block: Some(10),
+            to_block: Some(12),
+            ..Default::default()
+        };
+        metadata.fill_missing(Metadata {
+            chain_id: Some(parse_chain_id("SN_SEPOLIA").unwrap()),
+            spec_version: Some("0.9.0".to_string()),
+            node_version: Some("v0.17.0".to_string()),
+            captured_at: Some("2025-05-01T00:00:00Z".to_string()),
+            from_block: Some(0),
+            to_block: Some(100),
+        });
+        assert_eq!(metadata.chain_id, Some(parse_chain_id("SN_MAIN").unwrap()));
+        assert_eq!(metadata.spec_version.as_deref(), Some("0.8.1"));
+        assert_eq!(metadata.node_version.as_deref(), Some("v0.17.0"));
+        assert_eq!(
+            metadata.captured_at.as_deref(),
+            Some("2025-05-01T00:00:00Z")
+        );
+        assert_eq!(
+            (metadata.from_block, metadata.to_block),
+            (Some(10), Some(12))
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use eyre::anyhow;
+use serde_json::json;
+use starknet::core::types::{BlockId, Felt, requests::GetEventsRequest};
+use starknet::providers::Url;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::block_hashes::BlockHashes;
+use crate::block_ref::BlockRef;
+use crate::error_case::{
+    BLOCK_NOT_FOUND, INVALID_CONTINUATION_TOKEN, PAGE_SIZE_TOO_BIG, TOO_MANY_KEYS_IN_FILTER,
+};
+use crate::fixture_set::{FixtureSet, load_events};
+
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// fixture-backed starknet_getEvents server, behind the serve_rpc example and
+// the checker's own tests
+pub struct Server {
+    pub fixtures: FixtureSet,
+    pub max_chunk_size: u64,
+    pub max_keys: usize,
+    pub spec_version: String,
+    pub chain_id: Felt,
+    // the last block of any fixture stands in for the chain head
+    pub head: u64,
+}
+
+impl Server {
+    pub fn handle(&self, body: &[u8]) -> serde_json::Value {
+        let request: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(v) => v,
+            Err(err) => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": err.to_string() }
+                });
+            }
+        };
+        let id = request
+            .get("id")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+        let method = request
+            .get("method")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default();
+        let params = request
+            .get("params")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+        let outcome = match method {
+            "starknet_getEvents" => self.get_events(params),
+            "starknet_specVersion" => Ok(json!(self.spec_version)),
+            "starknet_chainId" => Ok(json!(format!("{:#x}", self.chain_id))),
+            "starknet_blockNumber" => Ok(json!(self.head)),
+            "starknet_syncing" => Ok(json!(false)),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method {} not supported", method),
+            )),
+        };
+        match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message }
+            }),
+        }
+    }
+
+    fn get_events(&self, params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+        let request: GetEventsRequest = serde_json::from_value(params)
+            .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+        let filter = request.filter.event_filter;
+        let page_request = request.filter.result_page_request;
+        if page_request.chunk_size == 0 {
+            return Err(RpcError::new(INVALID_PARAMS, "chunk size must be positive"));
+        }
+        if page_request.chunk_size > self.max_chunk_size {
+            return Err(RpcError::new(
+                PAGE_SIZE_TOO_BIG,
+                "Requested page size is too big",
+            ));
+        }
+
+        let key_count: usize = filter.keys.iter().flatten().map(|alt| alt.len()).sum();
+        if key_count > self.max_keys {
+            return Err(RpcError::new(
+                TOO_MANY_KEYS_IN_FILTER,
+                "Too many keys provided in a filter",
+            ));
+        }
+
+        let from_block = block_ref(filter.from_block)?;
+        let to_block = block_ref(filter.to_block)?;
+        for block in [&from_block, &to_block] {
+            if block.number().is_some_and(|n| n > self.head) {
+                return Err(RpcError::new(BLOCK_NOT_FOUND, "Block not found"));
+            }
+        }
+
+        // tokens are bound to the filter, so check them before looking for
+        // a fixture: a token reused with another filter is invalid rather
+        // than unknown
+        let fingerprint = fingerprint(&from_block, &to_block, &filter.address, &filter.keys);
+        let offset = match page_request.continuation_token {
+            Some(token) => parse_token(&token, fingerprint).ok_or_else(|| {
+                RpcError::new(INVALID_CONTINUATION_TOKEN, "Invalid continuation token")
+            })?,
+            None => 0,
+        };
+        let fixture = self
+            .fixtures
+            .find(&from_block, &to_block, &filter.address, &filter.keys)
+            .ok_or_else(|| RpcError::new(INTERNAL_ERROR, "no fixture matches filter"))?;
+
+        let mut events = load_events(&fixture.path)
+            .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?;
+        let block_hashes = match &fixture.block_hashes {
+            Some(path) => BlockHashes::load(path)
+                .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?,
+            None => BlockHashes::default(),
+        };
+        for event in events.iter_mut() {
+            if let Some(block_number) = event["block_number"].as_u64() {
+                // fixtures don't store hashes; blocks missing from the map
+                // get a stand-in
+                let hash = block_hashes
+                    .hashes
+                    .get(&block_number)
+                    .copied()
+                    .unwrap_or(Felt::from(block_number));
+                event["block_hash"] = json!(format!("{:#x}", hash));
+            }
+        }
+        if offset > events.len() {
+            return Err(RpcError::new(
+                INVALID_CONTINUATION_TOKEN,
+                "Invalid continuation token",
+            ));
+        }
+
+        let end = offset
+            .saturating_add(page_request.chunk_size as usize)
+            .min(events.len());
+        let page = &events[offset..end];
+        let ret = if end < events.len() {
+            json!({
+                "events": page,
+                "continuation_token": format!("{:x}-{}", fingerprint, end),
+            })
+        } else {
+            json!({ "events": page })
+        };
+        Ok(ret)
+    }
+}
+
+// tags and hashes are served when a fixture was recorded with exactly them
+fn block_ref(block_id: Option<BlockId>) -> Result<BlockRef, RpcError> {
+    match block_id {
+        Some(block_id) => Ok(block_id.into()),
+        None => Err(RpcError::new(
+            INVALID_PARAMS,
+            "fixtures require explicit block range",
+        )),
+    }
+}
+
+fn fingerprint(
+    from_block: &BlockRef,
+    to_block: &BlockRef,
+    address: &Option<Felt>,
+    keys: &Option<Vec<Vec<Felt>>>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    from_block.hash(&mut hasher);
+    to_block.hash(&mut hasher);
+    address.hash(&mut hasher);
+    keys.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn parse_token(token: &str, fingerprint: u64) -> Option<usize> {
+    let (head, tail) = token.split_once('-')?;
+    let token_fingerprint = u64::from_str_radix(head, 16).ok()?;
+    if token_fingerprint != fingerprint {
+        return None;
+    }
+
+    tail.parse::<usize>().ok()
+}
+
+pub async fn serve_connection(server: Arc<Server>, stream: TcpStream) -> eyre::Result<()> {
+    stream.set_nodelay(true)?;
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+    loop {
+        let mut content_length = None;
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                return Err(anyhow!("connection closed inside headers"));
+            }
+
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                content_length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+
+        let len = content_length.ok_or_else(|| anyhow!("request without content length"))?;
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await?;
+        let response = server.handle(&body).to_string();
+        let message = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        write_half.write_all(message.as_bytes()).await?;
+    }
+}
+
+pub async fn serve(server: Arc<Server>, listener: TcpListener) -> eyre::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(server, stream).await {
+                tracing::warn!("connection from {} failed: {}", peer, err);
+            }
+        });
+    }
+}
+
+// on a free local port, for tests
+pub async fn spawn(server: Server) -> eyre::Result<Url> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?).parse()?;
+    tokio::spawn(serve(Arc::new(server), listener));
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::Felt;
+
+    use std::fs;
+
+    use super::{Server, fingerprint, parse_token};
+    use crate::block_ref::BlockRef;
+    use crate::error_case::{INVALID_CONTINUATION_TOKEN, PAGE_SIZE_TOO_BIG};
+    use crate::fixture_set::FixtureSet;
+
+    fn server(fixture_dir: &std::path::Path) -> Server {
+        let events: Vec<String> = (10..13)
+            .map(|block_number| {
+                json!({
+                    "block_number": block_number,
+                    "from_address": "0x1",
+                    "keys": ["0x2"],
+                    "data": [],
+                    "transaction_hash": "0x3"
+                })
+                .to_string()
+            })
+            .collect();
+        fs::write(fixture_dir.join("10+2.jsonl"), events.join("\n")).unwrap();
+        Server {
+            fixtures: FixtureSet::load(fixture_dir).unwrap(),
+            max_chunk_size: 2,
+            max_keys: 16,
+            spec_version: "0.8.1".to_string(),
+            chain_id: Felt::ONE,
+            head: 12,
+        }
+    }
+
+    fn get_events(server: &Server, chunk_size: u64, token: Option<&str>) -> serde_json::Value {
+        let mut filter = json!({
+            "from_block": { "block_number": 10 },
+            "to_block": { "block_number": 12 },
+            "chunk_size": chunk_size
+        });
+        if let Some(token) = token {
+            filter["continuation_token"] = json!(token);
+        }
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_getEvents",
+            "params": { "filter": filter }
+        });
+        server.handle(request.to_string().as_bytes())
+    }
+
+    #[test]
+    fn tokens_are_bound_to_the_filter() {
+        let a = fingerprint(&BlockRef::Number(1), &BlockRef::Number(2), &None, &None);
+        let b = fingerprint(&BlockRef::Number(1), &BlockRef::Number(3), &None, &None);
+        assert_eq!(parse_token(&format!("{:x}-5", a), a), Some(5));
+        assert_eq!(parse_token(&format!("{:x}-5", a), b), None);
+        assert_eq!(parse_token(&format!("{:x}", a), a), None);
+        assert_eq!(parse_token(&format!("{:x}-x", a), a), None);
+        assert_eq!(parse_token("zz-5", a), None);
+    }
+
+    #[test]
+    fn pages_follow_their_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(dir.path());
+        let first = get_events(&server, 2, None);
+        let events = first["result"]["events"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["block_hash"], json!("0xa"));
+        let token = first["result"]["continuation_token"].as_str().unwrap();
+
+        let last = get_events(&server, 2, Some(token));
+        let events = last["result"]["events"].as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["block_number"], json!(12));
+        assert!(last["result"].get("continuation_token").is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(dir.path());
+        assert_eq!(
+            get_events(&server, 3, None)["error"]["code"],
+            json!(PAGE_SIZE_TOO_BIG)
+        );
+        assert_eq!(
+            get_events(&server, 2, Some("0-1"))["error"]["code"],
+            json!(INVALID_CONTINUATION_TOKEN)
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use eyre::anyhow;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use starknet::core::types::{
+    BlockHeader, ConfirmedBlockId, EmittedEvent, Felt, ReorgData, SubscriptionId,
+    requests::{
+        SubscribeEventsRequest, SubscribeNewHeadsRequest, SubscriptionEventsRequest,
+        SubscriptionNewHeadsRequest, SubscriptionReorgRequest,
+    },
+};
+use starknet::providers::{
+    ProviderRequestData, StreamUpdateData, Url,
+    jsonrpc::{JsonRpcRequest, JsonRpcStreamUpdate},
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio_tungstenite::tungstenite::Message;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::block_ref::BlockRef;
+use crate::error_case::BLOCK_NOT_FOUND;
+use crate::fixture_set::{FixtureSet, load_events};
+use crate::matcher::EventMatcher;
+
+const INVALID_SUBSCRIPTION_ID: i64 = 66;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+// a node replays every block up to its head, the first few are all a client
+// waiting for one particular block needs
+const HEADS_REPLAYED: u64 = 16;
+
+// a reorg notification sent to event subscriptions once their replay has
+// passed after_block
+pub struct ScriptedReorg {
+    pub after_block: u64,
+    pub reorg: ReorgData,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// fixture-backed starknet_subscribeEvents and starknet_subscribeNewHeads
+// server, behind the serve_ws example and the checker's own tests
+pub struct Server {
+    fixtures: FixtureSet,
+    reorgs: Vec<ScriptedReorg>,
+    next_id: AtomicU64,
+    // the block after the last of any fixture stands in for the chain head,
+    // so a head past every fixture range exists
+    head: u64,
+}
+
+struct Connection {
+    server: Arc<Server>,
+    queue: UnboundedSender<(SubscriptionId, String)>,
+    active: HashSet<SubscriptionId>,
+}
+
+impl Server {
+    pub fn new(fixtures: FixtureSet, reorgs: Vec<ScriptedReorg>) -> Self {
+        let head = fixtures
+            .entries
+            .iter()
+            .filter_map(|e| e.to_block.number())
+            .max()
+            .unwrap_or_default()
+            + 1;
+        Self {
+            fixtures,
+            reorgs,
+            next_id: AtomicU64::new(1),
+            head,
+        }
+    }
+
+    fn replay_events(
+        &self,
+        from_block: u64,
+        address: &Option<Felt>,
+        keys: &Option<Vec<Vec<Felt>>>,
+    ) -> eyre::Result<Vec<EmittedEvent>> {
+        // a fixture recorded for exactly this subscription wins over the
+        // unfiltered ranges, which may not cover its blocks
+        let exact = self
+            .fixtures
+            .entries
+            .iter()
+            .filter(|e| {
+                e.from_block == BlockRef::Number(from_block)
+                    && e.address == *address
+                    && e.keys == *keys
+            })
+            .max_by_key(|e| e.to_block.number());
+        let mut sources: Vec<_> = match exact {
+            Some(entry) => vec![entry],
+            None => self
+                .fixtures
+                .entries
+                .iter()
+                .filter(|e| {
+                    e.address.is_none()
+                        && e.keys.is_none()
+                        && e.from_block.number().is_some()
+                        && e.to_block.number().is_some_and(|n| n >= from_block)
+                })
+                .collect(),
+        };
+        sources.sort_by_key(|e| e.from_block.number());
+
+        let matcher = EventMatcher::new(from_block, u64::MAX, *address, keys.clone());
+        let mut events = Vec::new();
+        for entry in sources {
+            for value in load_events(&entry.path)? {
+                if matcher.is_match(&value)? {
+                    events.push(serde_json::from_value::<EmittedEvent>(value)?);
+                }
+            }
+        }
+
+        events.sort_by_key(|e| e.block_number);
+        Ok(events)
+    }
+}
+
+impl Connection {
+    fn handle(&mut self, text: &str) -> serde_json::Value {
+        let request: JsonRpcRequest = match serde_json::from_str(text) {
+            Ok(r) => r,
+            Err(err) => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": INVALID_REQUEST, "message": err.to_string() }
+                });
+            }
+        };
+        let outcome = match request.data {
+            ProviderRequestData::SubscribeEvents(req) => self.subscribe_events(req),
+            ProviderRequestData::SubscribeNewHeads(req) => self.subscribe_new_heads(req),
+            ProviderRequestData::Unsubscribe(req) => {
+                if self.active.remove(&req.subscription_id) {
+                    Ok(json!(true))
+                } else {
+                    Err(RpcError::new(
+                        INVALID_SUBSCRIPTION_ID,
+                        "Invalid subscription id",
+                    ))
+                }
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, "method not supported")),
+        };
+        match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "error": { "code": err.code, "message": err.message }
+            }),
+        }
+    }
+
+    fn subscribe_events(
+        &mut self,
+        req: SubscribeEventsRequest,
+    ) -> Result<serde_json::Value, RpcError> {
+        let from_block = match req.block_id {
+            Some(ConfirmedBlockId::Number(n)) => n,
+            Some(ConfirmedBlockId::Hash(_)) => {
+                return Err(RpcError::new(BLOCK_NOT_FOUND, "Block not found"));
+            }
+            // fixtures are historical, so nothing is newer than the head
+            Some(ConfirmedBlockId::Latest) | None => u64::MAX,
+        };
+        let events = self
+            .server
+            .replay_events(from_block, &req.from_address, &req.keys)
+            .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?;
+
+        let subscription_id = SubscriptionId(
+            self.server
+                .next_id
+                .fetch_add(1, Ordering::Relaxed)
+                .to_string(),
+        );
+        self.active.insert(subscription_id.clone());
+
+        let mut reorgs = self
+            .server
+            .reorgs
+            .iter()
+            .filter(|r| r.after_block >= from_block)
+            .peekable();
+        for event in events {
+            let block_number = event.block_number.unwrap_or_default();
+            while let Some(scripted) = reorgs.next_if(|r| r.after_block < block_number) {
+                self.enqueue_reorg(&subscription_id, scripted)?;
+            }
+
+            let update = JsonRpcStreamUpdate {
+                data: StreamUpdateData::SubscriptionEvents(SubscriptionEventsRequest {
+                    subscription_id: subscription_id.clone(),
+                    result: event,
+                }),
+            };
+            self.enqueue(&subscription_id, &update)?;
+        }
+
+        for scripted in reorgs {
+            self.enqueue_reorg(&subscription_id, scripted)?;
+        }
+
+        Ok(json!(subscription_id))
+    }
+
+    // headers are made up, only their block numbers mean anything
+    fn subscribe_new_heads(
+        &mut self,
+        req: SubscribeNewHeadsRequest,
+    ) -> Result<serde_json::Value, RpcError> {
+        let head = self.server.head;
+        let from_block = match req.block_id {
+            Some(ConfirmedBlockId::Number(n)) if n > head => {
+                return Err(RpcError::new(BLOCK_NOT_FOUND, "Block not found"));
+            }
+            Some(ConfirmedBlockId::Number(n)) => n,
+            Some(ConfirmedBlockId::Hash(_)) => {
+                return Err(RpcError::new(BLOCK_NOT_FOUND, "Block not found"));
+            }
+            Some(ConfirmedBlockId::Latest) | None => head,
+        };
+
+        let subscription_id = SubscriptionId(
+            self.server
+                .next_id
+                .fetch_add(1, Ordering::Relaxed)
+                .to_string(),
+        );
+        self.active.insert(subscription_id.clone());
+        for block_number in from_block..=head.min(from_block + HEADS_REPLAYED - 1) {
+            let header: BlockHeader = serde_json::from_value(json!({
+                "block_hash": format!("{:#x}", Felt::from(block_number)),
+                "parent_hash": format!("{:#x}", Felt::from(block_number.saturating_sub(1))),
+                "block_number": block_number,
+                "new_root": "0x0",
+                "timestamp": 0,
+                "sequencer_address": "0x0",
+                "l1_gas_price": { "price_in_fri": "0x0", "price_in_wei": "0x0" },
+                "l2_gas_price": { "price_in_fri": "0x0", "price_in_wei": "0x0" },
+                "l1_data_gas_price": { "price_in_fri": "0x0", "price_in_wei": "0x0" },
+                "l1_da_mode": "BLOB",
+                "starknet_version": "0.13.5"
+            }))
+            .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?;
+            let update = JsonRpcStreamUpdate {
+                data: StreamUpdateData::SubscriptionNewHeads(SubscriptionNewHeadsRequest {
+                    subscription_id: subscription_id.clone(),
+                    result: header,
+                }),
+            };
+            self.enqueue(&subscription_id, &update)?;
+        }
+
+        Ok(json!(subscription_id))
+    }
+
+    fn enqueue_reorg(
+        &self,
+        subscription_id: &SubscriptionId,
+        scripted: &ScriptedReorg,
+    ) -> Result<(), RpcError> {
+        let update = JsonRpcStreamUpdate {
+            data: StreamUpdateData::SubscriptionReorg(SubscriptionReorgRequest {
+                subscription_id: subscription_id.clone(),
+                result: scripted.reorg.clone(),
+            }),
+        };
+        self.enqueue(subscription_id, &update)
+    }
+
+    fn enqueue(
+        &self,
+        subscription_id: &SubscriptionId,
+        update: &JsonRpcStreamUpdate,
+    ) -> Result<(), RpcError> {
+        let text = serde_json::to_string(update)
+            .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?;
+        self.queue
+            .send((subscription_id.clone(), text))
+            .map_err(|_| RpcError::new(INTERNAL_ERROR, "connection closed"))
+    }
+}
+
+pub fn load_reorg_script(path: &Path) -> eyre::Result<Vec<ScriptedReorg>> {
+    let contents = fs::read_to_string(path)?;
+    let items: Vec<serde_json::Value> = serde_json::from_str(&contents)?;
+    let mut reorgs = Vec::new();
+    for item in items {
+        let after_block = item["after_block"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("reorg without after_block"))?;
+        let reorg: ReorgData = serde_json::from_value(item)?;
+        reorgs.push(ScriptedReorg { after_block, reorg });
+    }
+
+    reorgs.sort_by_key(|r| r.after_block);
+    Ok(reorgs)
+}
+
+pub async fn serve_connection(server: Arc<Server>, stream: TcpStream) -> eyre::Result<()> {
+    let ws = tokio_tungstenite::accept_async(stream).await?;
+    let (mut sink, mut source) = ws.split();
+    let (queue_tx, mut queue_rx) = unbounded_channel::<(SubscriptionId, String)>();
+    let mut connection = Connection {
+        server,
+        queue: queue_tx,
+        active: HashSet::new(),
+    };
+    loop {
+        tokio::select! {
+            message = source.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        let reply = connection.handle(text.as_str());
+                        sink.send(Message::Text(reply.to_string().into())).await?;
+                    }
+                    Some(Ok(Message::Ping(payload))) => {
+                        sink.send(Message::Pong(payload)).await?;
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                }
+            }
+            Some((subscription_id, text)) = queue_rx.recv() => {
+                // updates queued before an unsubscribe are dropped
+                if connection.active.contains(&subscription_id) {
+                    sink.send(Message::Text(text.into())).await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn serve(server: Arc<Server>, listener: TcpListener) -> eyre::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(server, stream).await {
+                tracing::warn!("connection from {} failed: {}", peer, err);
+            }
+        });
+    }
+}
+
+// on a free local port, for tests
+pub async fn spawn(server: Server) -> eyre::Result<Url> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("ws://{}", listener.local_addr()?).parse()?;
+    tokio::spawn(serve(Arc::new(server), listener));
+    Ok(url)
+}
@@ -0,0 +1,368 @@
+use eyre::anyhow;
+
+use std::collections::HashMap;
+use std::fs;
+
+use starknet::core::types::EmittedEvent;
+
+use crate::felt::{FELT_FIELDS, canonical};
+use crate::outcome::Violation;
+use crate::spec::SpecVersion;
+
+// all a subscription event keeps once the typed client has decoded it;
+// newer fields like transaction_index are gone by then
+const TYPED_EVENT_FIELDS: [&str; 6] = [
+    "from_address",
+    "keys",
+    "data",
+    "block_hash",
+    "block_number",
+    "transaction_hash",
+];
+
+// how events are brought to the shape fixtures store before comparing;
+// applied to server responses in both modes and to fixtures themselves
+pub struct Profile {
+    pub drop: Vec<String>,
+    pub require: Vec<String>,
+    pub felt_fields: Vec<String>,
+    pub canonical_felts: bool,
+}
+
+impl Profile {
+    pub fn builtin(spec_version: &str) -> Option<Self> {
+        let drop: &[&str] = match spec_version {
+            "0.7" | "0.8" => &["block_hash"],
+            "0.9" => &[
+                "block_hash",
+                "transaction_index",
+                "event_index",
+                "finality_status",
+            ],
+            _ => return None,
+        };
+        Some(Self {
+            drop: drop.iter().map(|s| s.to_string()).collect(),
+            require: FELT_FIELDS.iter().map(|s| s.to_string()).collect(),
+            felt_fields: FELT_FIELDS.iter().map(|s| s.to_string()).collect(),
+            canonical_felts: false,
+        })
+    }
+
+    // the newest built-in profile not after spec_version; later versions
+    // are taken to keep the event shape until a profile says otherwise
+    pub fn for_spec_version(spec_version: SpecVersion) -> eyre::Result<Self> {
+        let builtin = ["0.9", "0.8", "0.7"]
+            .into_iter()
+            .find(|v| SpecVersion::parse(v).is_ok_and(|v| v <= spec_version))
+            .ok_or_else(|| {
+                anyhow!(
+                    "no built-in normalization profile for spec version {}, pass one with --normalization",
+                    spec_version
+                )
+            })?;
+        if builtin != spec_version.to_string() {
+            tracing::warn!(
+                "no normalization profile for spec version {}, using {}",
+                spec_version,
+                builtin
+            );
+        }
+
+        Self::builtin(builtin).ok_or_else(|| anyhow!("no built-in profile {}", builtin))
+    }
+
+    // a spec version, falling back like for_spec_version, or a JSON file
+    // with the profile's fields
+    pub fn load(name: &str) -> eyre::Result<Self> {
+        if let Some(profile) = Self::builtin(name) {
+            return Ok(profile);
+        }
+        // major.minor or major.minor.patch, as starknet_specVersion returns
+        // it; other names, like 0.8.json, are files
+        let parts: Vec<&str> = name.split('.').collect();
+        if (2..=3).contains(&parts.len())
+            && parts
+                .iter()
+                .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        {
+            return Self::for_spec_version(SpecVersion::parse(name)?);
+        }
+
+        let contents = fs::read_to_string(name)
+            .map_err(|err| anyhow!("no built-in normalization profile {}: {}", name, err))?;
+        let profile_map: HashMap<String, serde_json::Value> = serde_json::from_str(&contents)?;
+        let strings = |field: &str| -> eyre::Result<Vec<String>> {
+            match profile_map.get(field) {
+                Some(serde_json::Value::Array(items)) => items
+                    .iter()
+                    .map(|item| {
+                        item.as_str()
+                            .map(|s| s.to_string())
+                            .ok_or_else(|| anyhow!("{} must list field names", field))
+                    })
+                    .collect(),
+                Some(_) => Err(anyhow!("{} must list field names", field)),
+                None => Ok(Vec::new()),
+            }
+        };
+        Ok(Self {
+            drop: strings("drop")?,
+            require: strings("require")?,
+            felt_fields: match profile_map.get("felt_fields") {
+                Some(_) => strings("felt_fields")?,
+                None => FELT_FIELDS.iter().map(|s| s.to_string()).collect(),
+            },
+            canonical_felts: profile_map
+                .get("canonical_felts")
+                .and_then(|v| v.as_bool())
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn normalize_all(
+        &self,
+        events: Vec<serde_json::Value>,
+    ) -> eyre::Result<Vec<serde_json::Value>> {
+        events.into_iter().map(|e| self.normalize(e)).collect()
+    }
+
+    pub fn normalize(&self, event: serde_json::Value) -> eyre::Result<serde_json::Value> {
+        self.apply(event, &self.require)
+    }
+
+    // fields the typed event can't carry aren't required of it
+    pub fn normalize_typed(&self, event: &EmittedEvent) -> eyre::Result<serde_json::Value> {
+        let require: Vec<String> = self
+            .require
+            .iter()
+            .filter(|field| TYPED_EVENT_FIELDS.contains(&field.as_str()))
+            .cloned()
+            .collect();
+        self.apply(serde_json::to_value(event)?, &require)
+    }
+
+    fn apply(
+        &self,
+        event: serde_json::Value,
+        require: &[String],
+    ) -> eyre::Result<serde_json::Value> {
+        let serde_json::Value::Object(mut event_map) = event else {
+            return Err(anyhow!("event not an object: {}", event));
+        };
+
+        for field in require.iter() {
+            if !event_map.contains_key(field) {
+                return Err(Violation(format!(
+                    "event w/o {}: {}",
+                    field,
+                    serde_json::Value::Object(event_map)
+                ))
+                .into());
+            }
+        }
+
+        for field in self.drop.iter() {
+            event_map.remove(field);
+        }
+
+        if self.canonical_felts {
+            for field in self.felt_fields.iter() {
+                if let Some(value) = event_map.get_mut(field) {
+                    canonicalize(value)?;
+                }
+            }
+        }
+
+        Ok(serde_json::Value::Object(event_map))
+    }
+}
+
+fn canonicalize(value: &mut serde_json::Value) -> eyre::Result<()> {
+    match value {
+        serde_json::Value::String(s) => {
+            *s = canonical(s)?;
+        }
+        serde_json::Value::Array(items) => {
+            for item in items.iter_mut() {
+                canonicalize(item)?;
+            }
+        }
+        _ => return Err(anyhow!("unexpected felt type: {}", value)),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::core::types::{EmittedEvent, Felt};
+
+    use std::fs;
+
+    use super::Profile;
+    use crate::outcome::Violation;
+    use crate::spec::SpecVersion;
+
+    fn event() -> serde_json::Value {
+        json!({
+            "from_address": "0x01",
+            "keys": ["0x002"],
+            "data": [],
+            "block_hash": "0x3",
+            "block_number": 4,
+            "transaction_hash": "0x5",
+            "transaction_index": 6,
+            "event_index": 7
+        })
+    }
+
+    fn load(contents: &str) -> eyre::Result<Profile> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        fs::write(&path, contents).unwrap();
+        Profile::load(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn builtin_profiles_per_version() {
+        for v in ["0.7", "0.8"] {
+            assert_eq!(Profile::builtin(v).unwrap().drop, ["block_hash"], "{}", v);
+        }
+        let profile = Profile::builtin("0.9").unwrap();
+        assert_eq!(
+            profile.drop,
+            [
+                "block_hash",
+                "transaction_index",
+                "event_index",
+                "finality_status"
+            ]
+        );
+        assert!(!profile.canonical_felts);
+        assert!(Profile::builtin("0.6").is_none());
+        assert!(Profile::builtin("0.10").is_none());
+    }
+
+    #[test]
+    fn later_spec_versions_fall_back_to_newest_profile() {
+        let drop = |v| {
+            Profile::for_spec_version(SpecVersion::parse(v).unwrap())
+                .unwrap()
+                .drop
+                .len()
+        };
+        assert_eq!(drop("0.8"), 1);
+        assert_eq!(drop("0.9"), 4);
+        assert_eq!(drop("0.10"), 4);
+        assert_eq!(drop("1.0"), 4);
+        assert!(Profile::for_spec_version(SpecVersion::parse("0.6").unwrap()).is_err());
+        assert_eq!(Profile::load("0.10").unwrap().drop.len(), 4);
+    }
+
+    #[test]
+    fn patch_versions_load_their_minor_profile() {
+        assert_eq!(Profile::load("0.8.1").unwrap().drop.len(), 1);
+        assert_eq!(Profile::load("0.9.0").unwrap().drop.len(), 4);
+        assert_eq!(Profile::load("0.10.2").unwrap().drop.len(), 4);
+        assert!(Profile::load("0.6.1").is_err());
+        // not a version, so read as a (missing) file
+        let err = Profile::load("0.8.json").err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("no built-in normalization profile 0.8.json")
+        );
+    }
+
+    #[test]
+    fn loads_profile_file() {
+        let profile = load(
+            r#"{"drop": ["event_index"], "require": ["keys"], "felt_fields": ["keys"], "canonical_felts": true}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.drop, ["event_index"]);
+        assert_eq!(profile.require, ["keys"]);
+        assert_eq!(profile.felt_fields, ["keys"]);
+        assert!(profile.canonical_felts);
+
+        let profile = load("{}").unwrap();
+        assert!(profile.drop.is_empty());
+        assert!(profile.require.is_empty());
+        assert_eq!(profile.felt_fields.len(), 4);
+        assert!(!profile.canonical_felts);
+    }
+
+    #[test]
+    fn rejects_invalid_profile_files() {
+        for contents in [
+            r#"{"drop": "block_hash"}"#,
+            r#"{"require": [1]}"#,
+            r#"{"felt_fields": {}}"#,
+            "[]",
+            "not json",
+        ] {
+            assert!(load(contents).is_err(), "{}", contents);
+        }
+        assert!(Profile::load("/nonexistent/profile.json").is_err());
+    }
+
+    #[test]
+    fn drops_fields_and_keeps_felt_spelling() {
+        let profile = Profile::builtin("0.9").unwrap();
+        let normalized = profile.normalize(event()).unwrap();
+        assert_eq!(
+            normalized,
+            json!({
+                "from_address": "0x01",
+                "keys": ["0x002"],
+                "data": [],
+                "block_number": 4,
+                "transaction_hash": "0x5"
+            })
+        );
+    }
+
+    #[test]
+    fn canonical_felts_rewrites_felt_fields() {
+        let mut profile = Profile::builtin("0.8").unwrap();
+        profile.canonical_felts = true;
+        let normalized = profile.normalize(event()).unwrap();
+        assert_eq!(normalized["from_address"], "0x1");
+        assert_eq!(normalized["keys"], json!(["0x2"]));
+        let mut bad = event();
+        bad["data"] = json!([1]);
+        assert!(profile.normalize(bad).is_err());
+    }
+
+    #[test]
+    fn missing_required_field_is_a_violation() {
+        let profile = Profile::builtin("0.8").unwrap();
+        let mut event = event();
+        event.as_object_mut().unwrap().remove("transaction_hash");
+        let err = profile.normalize(event).unwrap_err();
+        assert!(err.downcast_ref::<Violation>().is_some());
+        assert!(profile.normalize(json!([])).is_err());
+    }
+
+    #[test]
+    fn typed_events_only_need_fields_they_carry() {
+        let mut profile = Profile::builtin("0.9").unwrap();
+        profile.require.push("transaction_index".to_string());
+        let typed = EmittedEvent {
+            from_address: Felt::ONE,
+            keys: vec![Felt::TWO],
+            data: Vec::new(),
+            block_hash: Some(Felt::THREE),
+            block_number: Some(4),
+            transaction_hash: Felt::from(5_u64),
+        };
+        let normalized = profile.normalize_typed(&typed).unwrap();
+        assert_eq!(normalized["block_number"], 4);
+        assert!(normalized.get("block_hash").is_none());
+        assert!(profile.normalize(event()).is_ok());
+        let mut untyped = event();
+        untyped.as_object_mut().unwrap().remove("transaction_index");
+        assert!(profile.normalize(untyped).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::diff::Diff;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Rpc,
+    Ws,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rpc => write!(f, "rpc"),
+            Self::Ws => write!(f, "ws"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+    pub event_count: usize,
+    pub page_count: Option<usize>,
+}
+
+// the server answered, but not the way the spec requires
+#[derive(Debug)]
+pub struct Violation(pub String);
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Violation {}
+
+// the server answered within the spec, but left nothing to check
+#[derive(Debug)]
+pub struct Unchecked(pub String);
+
+impl fmt::Display for Unchecked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Unchecked {}
+
+pub enum Outcome {
+    Pass,
+    Fail {
+        message: String,
+        details: Option<serde_json::Value>,
+    },
+    Error(String),
+    // not run, e.g. the node hasn't synced the range yet, or nothing to
+    // check came back
+    Skip(String),
+}
+
+pub struct FixtureResult {
+    pub fixture: PathBuf,
+    pub mode: Mode,
+    pub duration: Duration,
+    pub stats: Option<Stats>,
+    pub outcome: Outcome,
+}
+
+impl FixtureResult {
+    // stats are kept whatever the outcome, failures are where they matter
+    pub fn new(
+        fixture: PathBuf,
+        mode: Mode,
+        duration: Duration,
+        stats: Option<Stats>,
+        res: eyre::Result<()>,
+    ) -> Self {
+        let outcome = match res {
+            Ok(()) => Outcome::Pass,
+            Err(err) => {
+                let message = format!("{:#}", err);
+                if let Some(diff) = err.downcast_ref::<Diff>() {
+                    Outcome::Fail {
+                        message,
+                        details: Some(diff.to_json()),
+                    }
+                } else if err.downcast_ref::<Violation>().is_some() {
+                    Outcome::Fail {
+                        message,
+                        details: None,
+                    }
+                } else if err.downcast_ref::<Unchecked>().is_some() {
+                    Outcome::Skip(message)
+                } else {
+                    Outcome::Error(message)
+                }
+            }
+        };
+        Self {
+            fixture,
+            mode,
+            duration,
+            stats,
+            outcome,
+        }
+    }
+
+    pub fn skipped(fixture: PathBuf, mode: Mode, reason: String) -> Self {
+        Self {
+            fixture,
+            mode,
+            duration: Duration::ZERO,
+            stats: None,
+            outcome: Outcome::Skip(reason),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub results: Vec<FixtureResult>,
+}
+
+impl Summary {
+    pub fn record(&mut self, result: FixtureResult) {
+        match &result.outcome {
+            Outcome::Pass => tracing::debug!("{:?} ({}) passed", result.fixture, result.mode),
+            Outcome::Fail { message: msg, .. } => {
+                tracing::error!("{:?} ({}) failed: {}", result.fixture, result.mode, msg)
+            }
+            Outcome::Error(msg) => {
+                tracing::error!("{:?} ({}) errored: {}", result.fixture, result.mode, msg)
+            }
+            Outcome::Skip(reason) => {
+                tracing::info!("{:?} ({}) skipped: {}", result.fixture, result.mode, reason)
+            }
+        }
+
+        self.results.push(result);
+    }
+
+    pub fn count(&self) -> (usize, usize, usize, usize) {
+        let mut passed = 0;
+        let mut failed = 0;
+        let mut errors = 0;
+        let mut skipped = 0;
+        for result in self.results.iter() {
+            match result.outcome {
+                Outcome::Pass => passed += 1,
+                Outcome::Fail { .. } => failed += 1,
+                Outcome::Error(_) => errors += 1,
+                Outcome::Skip(_) => skipped += 1,
+            }
+        }
+
+        (passed, failed, errors, skipped)
+    }
+
+    pub fn is_success(&self) -> bool {
+        let (_, failed, errors, _) = self.count();
+        failed == 0 && errors == 0
+    }
+
+    pub fn print(&self) {
+        for result in self.results.iter() {
+            match &result.outcome {
+                Outcome::Pass => {}
+                Outcome::Fail { message: msg, .. } => {
+                    println!(
+                        "FAIL  {} ({}): {}",
+                        result.fixture.display(),
+                        result.mode,
+                        msg
+                    )
+                }
+                Outcome::Error(msg) => {
+                    println!(
+                        "ERROR {} ({}): {}",
+                        result.fixture.display(),
+                        result.mode,
+                        msg
+                    )
+                }
+                Outcome::Skip(reason) => {
+                    println!(
+                        "SKIP  {} ({}): {}",
+                        result.fixture.display(),
+                        result.mode,
+                        reason
+                    )
+                }
+            }
+        }
+
+        let (passed, failed, errors, skipped) = self.count();
+        if skipped > 0 {
+            println!(
+                "{} passed, {} failed, {} errors, {} skipped",
+                passed, failed, errors, skipped
+            );
+        } else {
+            println!("{} passed, {} failed, {} errors", passed, failed, errors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::{WrapErr, anyhow};
+    use serde_json::json;
+
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::{FixtureResult, Mode, Outcome, Summary, Unchecked, Violation};
+    use crate::diff::Diff;
+
+    fn result(res: eyre::Result<()>) -> FixtureResult {
+        FixtureResult::new(
+            PathBuf::from("1+2.jsonl"),
+            Mode::Rpc,
+            Duration::ZERO,
+            None,
+            res,
+        )
+    }
+
+    fn summary(outcomes: Vec<eyre::Result<()>>) -> Summary {
+        let mut summary = Summary::default();
+        for res in outcomes {
+            summary.record(result(res));
+        }
+        summary
+    }
+
+    #[test]
+    fn violations_fail() {
+        let res: eyre::Result<()> =
+            Err(Violation("page w/o events".to_string())).wrap_err("page size 5");
+        let Outcome::Fail { message, details } = result(res).outcome else {
+            panic!("violation doesn't fail");
+        };
+        assert_eq!(message, "page size 5: page w/o events");
+        assert!(details.is_none());
+    }
+
+    #[test]
+    fn diffs_fail_with_details() {
+        let event = json!({
+            "block_number": 1,
+            "transaction_hash": "0x1",
+            "from_address": "0x2",
+            "keys": [],
+            "data": []
+        });
+        let diff = Diff::compute(&[event], &[]).unwrap();
+        let outcome = result(Err(diff.into())).outcome;
+        assert!(matches!(
+            outcome,
+            Outcome::Fail {
+                details: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn other_errors_are_errors() {
+        let outcome = result(Err(anyhow!("connection refused"))).outcome;
+        assert!(matches!(outcome, Outcome::Error(message) if message == "connection refused"));
+    }
+
+    #[test]
+    fn unchecked_is_skipped() {
+        let res: eyre::Result<()> =
+            Err(Unchecked("nothing was checked".to_string())).wrap_err("page size 5");
+        assert!(matches!(result(res).outcome, Outcome::Skip(_)));
+        let skipped = FixtureResult::skipped(PathBuf::new(), Mode::Ws, "unsynced".to_string());
+        assert!(matches!(skipped.outcome, Outcome::Skip(_)));
+    }
+
+    #[test]
+    fn success_allows_skips_only() {
+        let skip = || Err(Unchecked("nothing was checked".to_string()).into());
+        assert!(summary(Vec::new()).is_success());
+        assert!(summary(vec![Ok(()), skip()]).is_success());
+        assert!(!summary(vec![Ok(()), Err(Violation("x".to_string()).into())]).is_success());
+        assert!(!summary(vec![Ok(()), Err(anyhow!("x"))]).is_success());
+
+        let summary = summary(vec![
+            Ok(()),
+            Err(Violation("x".to_string()).into()),
+            Err(anyhow!("x")),
+            skip(),
+            Ok(()),
+        ]);
+        assert_eq!(summary.count(), (2, 1, 1, 1));
+    }
+}
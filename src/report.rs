@@ -0,0 +1,247 @@
+use serde_json::json;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::outcome::{Outcome, Summary};
+
+pub fn write_junit(summary: &Summary, path: &Path) -> eyre::Result<()> {
+    let (passed, failed, errors, skipped) = summary.count();
+    let total_time: f64 = summary
+        .results
+        .iter()
+        .map(|r| r.duration.as_secs_f64())
+        .sum();
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        xml,
+        r#"<testsuite name="starknet-event-query" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}">"#,
+        passed + failed + errors + skipped,
+        failed,
+        errors,
+        skipped,
+        total_time
+    )?;
+    for result in summary.results.iter() {
+        writeln!(
+            xml,
+            r#"  <testcase name="{}" classname="{}" time="{:.3}">"#,
+            escape_xml(&result.fixture.display().to_string()),
+            result.mode,
+            result.duration.as_secs_f64()
+        )?;
+        if let Some(stats) = &result.stats {
+            writeln!(xml, "    <properties>")?;
+            writeln!(
+                xml,
+                r#"      <property name="event_count" value="{}"/>"#,
+                stats.event_count
+            )?;
+            if let Some(page_count) = stats.page_count {
+                writeln!(
+                    xml,
+                    r#"      <property name="page_count" value="{}"/>"#,
+                    page_count
+                )?;
+            }
+            writeln!(xml, "    </properties>")?;
+        }
+        match &result.outcome {
+            Outcome::Pass => {}
+            Outcome::Fail { message, .. } => {
+                let (head, body) = message.split_once('\n').unwrap_or((message, ""));
+                writeln!(
+                    xml,
+                    r#"    <failure message="{}">{}</failure>"#,
+                    escape_xml(head),
+                    escape_xml(body)
+                )?
+            }
+            Outcome::Error(msg) => writeln!(xml, r#"    <error message="{}"/>"#, escape_xml(msg))?,
+            Outcome::Skip(reason) => {
+                writeln!(xml, r#"    <skipped message="{}"/>"#, escape_xml(reason))?
+            }
+        }
+        writeln!(xml, "  </testcase>")?;
+    }
+    writeln!(xml, "</testsuite>")?;
+
+    fs::write(path, xml)?;
+    Ok(())
+}
+
+pub fn write_json(summary: &Summary, path: &Path) -> eyre::Result<()> {
+    let (passed, failed, errors, skipped) = summary.count();
+    let fixtures: Vec<serde_json::Value> = summary
+        .results
+        .iter()
+        .map(|result| {
+            let (outcome, message, details) = match &result.outcome {
+                Outcome::Pass => ("pass", None, None),
+                Outcome::Fail { message, details } => ("fail", Some(message), details.as_ref()),
+                Outcome::Error(msg) => ("error", Some(msg), None),
+                Outcome::Skip(reason) => ("skip", Some(reason), None),
+            };
+            json!({
+                "fixture": result.fixture.display().to_string(),
+                "mode": result.mode.to_string(),
+                "outcome": outcome,
+                "duration_ms": result.duration.as_millis() as u64,
+                "event_count": result.stats.map(|s| s.event_count),
+                "page_count": result.stats.and_then(|s| s.page_count),
+                "message": message,
+                "diff": details,
+            })
+        })
+        .collect();
+    let report = json!({
+        "passed": passed,
+        "failed": failed,
+        "errors": errors,
+        "skipped": skipped,
+        "fixtures": fixtures,
+    });
+
+    fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&apos;"),
+            _ => ret.push(c),
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::{escape_xml, write_json, write_junit};
+    use crate::outcome::{FixtureResult, Mode, Outcome, Stats, Summary};
+
+    fn summary() -> Summary {
+        let result = |name: &str, millis, stats, outcome| FixtureResult {
+            fixture: PathBuf::from(name),
+            mode: Mode::Rpc,
+            duration: Duration::from_millis(millis),
+            stats,
+            outcome,
+        };
+        Summary {
+            results: vec![
+                result(
+                    "1+2.jsonl",
+                    1500,
+                    Some(Stats {
+                        event_count: 7,
+                        page_count: Some(2),
+                    }),
+                    Outcome::Pass,
+                ),
+                result(
+                    "3w1.jsonl",
+                    250,
+                    Some(Stats {
+                        event_count: 3,
+                        page_count: None,
+                    }),
+                    Outcome::Fail {
+                        message: "1 missing <a & \"b\">\n  missing block 3".to_string(),
+                        details: None,
+                    },
+                ),
+                result("4.jsonl", 0, None, Outcome::Error("refused".to_string())),
+                result(
+                    "5.jsonl",
+                    0,
+                    None,
+                    Outcome::Skip("node head is 4".to_string()),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn escapes_xml_specials() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">&'"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;"
+        );
+        assert_eq!(escape_xml("block 3"), "block 3");
+    }
+
+    #[test]
+    fn junit_has_a_testcase_per_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("junit.xml");
+        write_junit(&summary(), &path).unwrap();
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="starknet-event-query" tests="4" failures="1" errors="1" skipped="1" time="1.750">
+  <testcase name="1+2.jsonl" classname="rpc" time="1.500">
+    <properties>
+      <property name="event_count" value="7"/>
+      <property name="page_count" value="2"/>
+    </properties>
+  </testcase>
+  <testcase name="3w1.jsonl" classname="rpc" time="0.250">
+    <properties>
+      <property name="event_count" value="3"/>
+    </properties>
+    <failure message="1 missing &lt;a &amp; &quot;b&quot;&gt;">  missing block 3</failure>
+  </testcase>
+  <testcase name="4.jsonl" classname="rpc" time="0.000">
+    <error message="refused"/>
+  </testcase>
+  <testcase name="5.jsonl" classname="rpc" time="0.000">
+    <skipped message="node head is 4"/>
+  </testcase>
+</testsuite>
+"#;
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn json_counts_and_lists_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        write_json(&summary(), &path).unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(report["passed"], json!(1));
+        assert_eq!(report["failed"], json!(1));
+        assert_eq!(report["errors"], json!(1));
+        assert_eq!(report["skipped"], json!(1));
+        let fixtures = report["fixtures"].as_array().unwrap();
+        let outcomes: Vec<&str> = fixtures
+            .iter()
+            .map(|f| f["outcome"].as_str().unwrap())
+            .collect();
+        assert_eq!(outcomes, ["pass", "fail", "error", "skip"]);
+        assert_eq!(fixtures[0]["duration_ms"], json!(1500));
+        assert_eq!(fixtures[0]["event_count"], json!(7));
+        assert_eq!(fixtures[0]["page_count"], json!(2));
+        assert_eq!(fixtures[0]["message"], json!(null));
+        assert_eq!(fixtures[1]["page_count"], json!(null));
+        assert_eq!(
+            fixtures[1]["message"],
+            json!("1 missing <a & \"b\">\n  missing block 3")
+        );
+        assert_eq!(fixtures[3]["message"], json!("node head is 4"));
+    }
+}
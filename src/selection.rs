@@ -0,0 +1,289 @@
+use eyre::anyhow;
+use glob::Pattern;
+
+use crate::config::Cli;
+use crate::manifest::{Case, InvalidCase};
+
+// which of the directory's cases a run covers
+pub struct Selection {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    // inclusive, either end may be open
+    blocks: Option<(Option<u64>, Option<u64>)>,
+    filtered: Option<bool>,
+    // 1-based shard index, shard count
+    shard: Option<(usize, usize)>,
+}
+
+impl Selection {
+    pub fn from_cli(cli: &Cli) -> eyre::Result<Self> {
+        let patterns = |globs: &[String]| -> eyre::Result<Vec<Pattern>> {
+            globs
+                .iter()
+                .map(|glob| {
+                    Pattern::new(glob).map_err(|err| anyhow!("invalid glob {}: {}", glob, err))
+                })
+                .collect()
+        };
+        let filtered = if cli.filtered_only {
+            Some(true)
+        } else if cli.unfiltered_only {
+            Some(false)
+        } else {
+            None
+        };
+        Ok(Self {
+            include: patterns(&cli.include)?,
+            exclude: patterns(&cli.exclude)?,
+            blocks: cli.blocks.as_deref().map(parse_blocks).transpose()?,
+            filtered,
+            shard: cli.shard.as_deref().map(parse_shard).transpose()?,
+        })
+    }
+
+    // sharding comes last, so every machine splits the same list
+    pub fn apply(&self, mut cases: Vec<Case>) -> Vec<Case> {
+        cases.retain(|case| self.matches(case));
+        if let Some((index, count)) = self.shard {
+            cases = cases
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| i % count == index - 1)
+                .map(|(_, case)| case)
+                .collect();
+        }
+
+        cases
+    }
+
+    // only the name of a case that couldn't be read is known; the first
+    // shard reports them, so a sharded run does once
+    pub fn apply_invalid(&self, mut invalid: Vec<InvalidCase>) -> Vec<InvalidCase> {
+        if self.shard.is_some_and(|(index, _)| index != 1) {
+            return Vec::new();
+        }
+
+        invalid.retain(|case| self.matches_name(&case.name));
+        invalid
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+
+    fn matches(&self, case: &Case) -> bool {
+        if !self.matches_name(&case.name) {
+            return false;
+        }
+
+        if let Some(filtered) = self.filtered
+            && case.is_filtered() != filtered
+        {
+            return false;
+        }
+
+        // ranges not made of block numbers can't be placed
+        if let Some((from, to)) = self.blocks {
+            let (Some(case_from), Some(case_to)) = (case.from_block.number(), case.last_block())
+            else {
+                return false;
+            };
+            if from.is_some_and(|from| case_to < from) || to.is_some_and(|to| case_from > to) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+fn parse_blocks(s: &str) -> eyre::Result<(Option<u64>, Option<u64>)> {
+    let (from, to) = s
+        .split_once("..")
+        .ok_or_else(|| anyhow!("block range not from..to: {}", s))?;
+    let bound = |b: &str| -> eyre::Result<Option<u64>> {
+        if b.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(
+                str::parse::<u64>(b).map_err(|_| anyhow!("invalid block number: {}", b))?,
+            ))
+        }
+    };
+    let (from, to) = (bound(from)?, bound(to)?);
+    if let (Some(from), Some(to)) = (from, to)
+        && from > to
+    {
+        return Err(anyhow!("empty block range {}..{}", from, to));
+    }
+
+    Ok((from, to))
+}
+
+fn parse_shard(s: &str) -> eyre::Result<(usize, usize)> {
+    let (index, count) = s
+        .split_once('/')
+        .ok_or_else(|| anyhow!("shard not i/n: {}", s))?;
+    let index = str::parse::<usize>(index).map_err(|_| anyhow!("invalid shard: {}", s))?;
+    let count = str::parse::<usize>(count).map_err(|_| anyhow!("invalid shard: {}", s))?;
+    if index == 0 || index > count {
+        return Err(anyhow!("shard {} out of 1..={}", index, count));
+    }
+
+    Ok((index, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use glob::Pattern;
+    use starknet::core::types::Felt;
+
+    use std::path::PathBuf;
+
+    use super::{Selection, parse_blocks, parse_shard};
+    use crate::block_ref::BlockRef;
+    use crate::manifest::{Case, InvalidCase};
+
+    fn case(name: &str, from_block: u64, to_block: u64, filtered: bool) -> Case {
+        Case {
+            name: name.to_string(),
+            fixture: PathBuf::from(format!("{}.jsonl", name)),
+            from_block: BlockRef::Number(from_block),
+            to_block: BlockRef::Number(to_block),
+            covered_to_block: None,
+            address: filtered.then_some(Felt::ONE),
+            keys: None,
+            error: None,
+            description: None,
+            base: None,
+            block_hashes: None,
+            page_sizes: None,
+        }
+    }
+
+    fn selection() -> Selection {
+        Selection {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            blocks: None,
+            filtered: None,
+            shard: None,
+        }
+    }
+
+    fn names(cases: Vec<Case>) -> Vec<String> {
+        cases.into_iter().map(|c| c.name).collect()
+    }
+
+    fn cases() -> Vec<Case> {
+        vec![
+            case("0+10", 0, 10, false),
+            case("0+10w1", 0, 10, true),
+            case("20+10", 20, 30, false),
+            case("20+10w1", 20, 30, true),
+            case("40+10", 40, 50, false),
+        ]
+    }
+
+    #[test]
+    fn shards_partition_the_selection() {
+        let mut seen = Vec::new();
+        for index in 1..=3 {
+            let shard = Selection {
+                shard: Some((index, 3)),
+                ..selection()
+            };
+            let names = names(shard.apply(cases()));
+            assert!(!names.is_empty());
+            seen.extend(names);
+        }
+        seen.sort();
+        let mut all = names(cases());
+        all.sort();
+        assert_eq!(seen, all);
+    }
+
+    #[test]
+    fn shards_split_after_filtering() {
+        let shard = Selection {
+            filtered: Some(false),
+            shard: Some((2, 2)),
+            ..selection()
+        };
+        assert_eq!(names(shard.apply(cases())), ["20+10"]);
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let globs = Selection {
+            include: vec![Pattern::new("*+10").unwrap()],
+            exclude: vec![Pattern::new("4*").unwrap()],
+            ..selection()
+        };
+        assert_eq!(names(globs.apply(cases())), ["0+10", "20+10"]);
+    }
+
+    #[test]
+    fn blocks_select_overlapping_ranges() {
+        let blocks = Selection {
+            blocks: Some((Some(25), Some(40))),
+            ..selection()
+        };
+        assert_eq!(names(blocks.apply(cases())), ["20+10", "20+10w1", "40+10"]);
+        let open = Selection {
+            blocks: Some((None, Some(5))),
+            ..selection()
+        };
+        assert_eq!(names(open.apply(cases())), ["0+10", "0+10w1"]);
+    }
+
+    #[test]
+    fn invalid_cases_go_to_the_first_shard_by_name() {
+        let invalid = || {
+            ["bad", "worse"]
+                .map(|name| InvalidCase {
+                    name: name.to_string(),
+                    fixture: PathBuf::from(format!("{}.jsonl", name)),
+                    reason: "unreadable".to_string(),
+                })
+                .into()
+        };
+        let names = |invalid: Vec<InvalidCase>| -> Vec<String> {
+            invalid.into_iter().map(|c| c.name).collect()
+        };
+        let first = Selection {
+            blocks: Some((Some(25), Some(40))),
+            exclude: vec![Pattern::new("w*").unwrap()],
+            shard: Some((1, 2)),
+            ..selection()
+        };
+        assert_eq!(names(first.apply_invalid(invalid())), ["bad"]);
+        let second = Selection {
+            shard: Some((2, 2)),
+            ..selection()
+        };
+        assert!(second.apply_invalid(invalid()).is_empty());
+    }
+
+    #[test]
+    fn parses_block_ranges() {
+        assert_eq!(parse_blocks("1..2").unwrap(), (Some(1), Some(2)));
+        assert_eq!(parse_blocks("..2").unwrap(), (None, Some(2)));
+        assert_eq!(parse_blocks("1..").unwrap(), (Some(1), None));
+        assert_eq!(parse_blocks("..").unwrap(), (None, None));
+        assert_eq!(parse_blocks("2..2").unwrap(), (Some(2), Some(2)));
+        for s in ["1", "a..2", "1..-2", "3..2"] {
+            assert!(parse_blocks(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parses_shards() {
+        assert_eq!(parse_shard("1/4").unwrap(), (1, 4));
+        assert_eq!(parse_shard("4/4").unwrap(), (4, 4));
+        for s in ["0/4", "5/4", "1", "1/0", "a/4"] {
+            assert!(parse_shard(s).is_err(), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use eyre::anyhow;
+use starknet::providers::Url;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpecVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl SpecVersion {
+    // accepts "0.8", patch versions like "0.8.1" as reported by
+    // starknet_specVersion, and path style "v0_8"
+    pub fn parse(s: &str) -> eyre::Result<Self> {
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let mut parts = s.split(['.', '_']);
+        let mut next = || -> eyre::Result<u32> {
+            let part = parts
+                .next()
+                .ok_or_else(|| anyhow!("invalid spec version: {}", s))?;
+            str::parse::<u32>(part).map_err(|_| anyhow!("invalid spec version: {}", s))
+        };
+        Ok(Self {
+            major: next()?,
+            minor: next()?,
+        })
+    }
+
+    // the versioned endpoint under base's path; an rpc or rpc/vX_Y ending
+    // base already has is replaced, a prefix before it is kept
+    pub fn endpoint(&self, base: &str) -> eyre::Result<Url> {
+        let mut url: Url = base.parse()?;
+        let mut segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+        if segments.len() >= 2
+            && segments[segments.len() - 2] == "rpc"
+            && segments
+                .last()
+                .is_some_and(|s| s.starts_with('v') && Self::parse(s).is_ok())
+        {
+            segments.truncate(segments.len() - 2);
+        } else if segments.last() == Some(&"rpc") {
+            segments.pop();
+        }
+
+        let mut path = String::new();
+        for segment in segments {
+            path.push('/');
+            path.push_str(segment);
+        }
+        url.set_path(&format!("{}/rpc/v{}_{}", path, self.major, self.minor));
+        Ok(url)
+    }
+
+    pub fn supports_subscriptions(&self) -> bool {
+        *self >= Self { major: 0, minor: 8 }
+    }
+
+    // 0.9 renamed the pending block
+    pub fn pending_tag(&self) -> &'static str {
+        if *self >= (Self { major: 0, minor: 9 }) {
+            "pre_confirmed"
+        } else {
+            "pending"
+        }
+    }
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpecVersion;
+
+    #[test]
+    fn parses_version_spellings() {
+        let expected = SpecVersion { major: 0, minor: 8 };
+        for s in ["0.8", "0.8.1", "v0_8", "0_8"] {
+            assert_eq!(SpecVersion::parse(s).unwrap(), expected, "{}", s);
+        }
+        for s in ["", "0", "v", "0.x", "latest"] {
+            assert!(SpecVersion::parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn versions_order_numerically() {
+        let v = |s| SpecVersion::parse(s).unwrap();
+        assert!(v("0.10") > v("0.9"));
+        assert!(v("1.0") > v("0.10"));
+        assert!(!v("0.7").supports_subscriptions());
+        assert!(v("0.8").supports_subscriptions());
+    }
+
+    #[test]
+    fn pending_tag_renamed_in_0_9() {
+        let v = |s| SpecVersion::parse(s).unwrap();
+        assert_eq!(v("0.8").pending_tag(), "pending");
+        assert_eq!(v("0.9").pending_tag(), "pre_confirmed");
+        assert_eq!(v("0.10").pending_tag(), "pre_confirmed");
+    }
+
+    #[test]
+    fn endpoint_replaces_rpc_path() {
+        let v = SpecVersion { major: 0, minor: 9 };
+        for base in [
+            "http://localhost:9545",
+            "http://localhost:9545/rpc",
+            "http://localhost:9545/rpc/v0_8",
+            "http://localhost:9545/rpc/v0_8/",
+        ] {
+            assert_eq!(
+                v.endpoint(base).unwrap().as_str(),
+                "http://localhost:9545/rpc/v0_9",
+                "{}",
+                base
+            );
+        }
+    }
+
+    #[test]
+    fn endpoint_keeps_path_prefix() {
+        let v = SpecVersion { major: 0, minor: 9 };
+        for base in [
+            "https://host/starknet/",
+            "https://host/starknet/rpc",
+            "https://host/starknet/rpc/v0_7",
+        ] {
+            assert_eq!(
+                v.endpoint(base).unwrap().as_str(),
+                "https://host/starknet/rpc/v0_9",
+                "{}",
+                base
+            );
+        }
+        assert_eq!(
+            v.endpoint("wss://host/node/v0_8").unwrap().as_str(),
+            "wss://host/node/v0_8/rpc/v0_9"
+        );
+    }
+}